@@ -0,0 +1,106 @@
+//! Variable values from `TF_VAR_name` environment variables, read from the
+//! process environment or from dotenv-style files.
+
+use std::fs;
+use std::path::Path;
+
+use crate::file::{no_position, Variable};
+use crate::hcl;
+
+const PREFIX: &str = "TF_VAR_";
+
+/// Values set in the environment of this process.
+pub fn process_values() -> Vec<Variable> {
+    values_from(std::env::vars())
+}
+
+fn values_from(vars: impl Iterator<Item = (String, String)>) -> Vec<Variable> {
+    let mut values: Vec<_> = vars
+        .filter_map(|(key, _)| {
+            let name = key.strip_prefix(PREFIX)?.to_string();
+            Some(Variable {
+                at: format!("${}", key),
+                name,
+                span: no_position(),
+            })
+        })
+        .collect();
+    values.sort_by(|a, b| a.name.cmp(&b.name));
+    values
+}
+
+/// Values set in a dotenv-style file: `KEY=value` lines, optionally
+/// starting with `export`. Other keys, comments and blank lines are skipped.
+pub fn read_env_file(path: &Path) -> Result<Vec<Variable>, String> {
+    let path_str = path.to_string_lossy().to_string();
+    let contents =
+        fs::read_to_string(path).map_err(|_| format!("Error: could not read file {}", path_str))?;
+    Ok(parse_env_file(&path_str, &contents))
+}
+
+fn parse_env_file(path: &str, contents: &str) -> Vec<Variable> {
+    let mut pos = hcl::Pos::start();
+    let mut values = vec![];
+    for line in contents.split_inclusive('\n') {
+        let statement = line.trim_start();
+        let statement = statement
+            .strip_prefix("export ")
+            .map(str::trim_start)
+            .unwrap_or(statement);
+        let key = statement.split('=').next().unwrap_or_default().trim_end();
+        if let (Some(name), true) = (key.strip_prefix(PREFIX), statement.contains('=')) {
+            let start = pos.advance(&line[..line.len() - statement.len()]);
+            values.push(Variable {
+                name: name.to_string(),
+                at: path.to_string(),
+                span: hcl::Span {
+                    start,
+                    end: start.advance(key),
+                },
+            });
+        }
+        pos = pos.advance(line);
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_values() {
+        let vars = vec![
+            ("TF_VAR_region".to_string(), "eu-west-1".to_string()),
+            ("HOME".to_string(), "/root".to_string()),
+            ("TF_VAR_count".to_string(), "2".to_string()),
+        ];
+        let values: Vec<_> = values_from(vars.into_iter())
+            .into_iter()
+            .map(|v| (v.name.clone(), v.location()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("count".to_string(), "$TF_VAR_count".to_string()),
+                ("region".to_string(), "$TF_VAR_region".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_env_file() {
+        let contents = "# CI settings\nexport TF_VAR_region=eu-west-1\n\n  TF_VAR_tags='{\"a\"=\"b\"}'\nAWS_PROFILE=ci\nTF_VAR_broken\n";
+        let values: Vec<_> = parse_env_file("ci.env", contents)
+            .into_iter()
+            .map(|v| (v.name.clone(), v.location()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("region".to_string(), "ci.env:2:8".to_string()),
+                ("tags".to_string(), "ci.env:4:3".to_string()),
+            ]
+        );
+    }
+}
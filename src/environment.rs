@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+
+use crate::file::{EntryType, File, Variable};
+use crate::module::Module;
+
+/// A named set of var files, such as `prod` with `envs/prod.tfvars`. Its
+/// values add to the ones the module directory itself provides.
+#[derive(Debug)]
+pub struct Environment {
+    pub name: String,
+    pub files: Vec<File>,
+}
+
+impl Environment {
+    /// Parses `name=path[,path...]` as given on the command line.
+    pub fn parse_spec(spec: &str) -> Result<(String, Vec<PathBuf>), String> {
+        let (name, paths) = match spec.split_once('=') {
+            Some((name, paths)) if !name.is_empty() && !paths.is_empty() => (name, paths),
+            _ => {
+                return Err(format!(
+                    "Environment {} should look like name=file.tfvars[,file.tfvars...]",
+                    spec
+                ))
+            }
+        };
+        Ok((
+            name.to_string(),
+            paths.split(',').map(PathBuf::from).collect(),
+        ))
+    }
+
+    /// Reads the var files of an environment. Files that can't be read or
+    /// parsed are skipped and their errors returned alongside.
+    pub fn load(name: String, paths: &[PathBuf]) -> (Environment, Vec<String>) {
+        let mut files = vec![];
+        let mut errors = vec![];
+        for path in paths {
+            match File::read_var_file(path) {
+                Ok(f) => files.push(f),
+                Err(e) => errors.push(e),
+            }
+        }
+        (Environment { name, files }, errors)
+    }
+
+    /// Values of this environment, including the ones every run of the
+    /// module gets: auto-loaded and `--var-file` files, environment
+    /// variables and flags. Other var files in the module directory only
+    /// count when they belong to the environment.
+    pub fn values(&self, module: &Module) -> Vec<Variable> {
+        let mut values: Vec<_> = module
+            .loaded_var_files()
+            .into_iter()
+            .chain(&self.files)
+            .flat_map(|f| f.get_var_entries(EntryType::Value))
+            .collect();
+        values.extend(module.extra_values.iter().cloned());
+        values
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cell {
+    Set,
+    /// Not set, but the variable has a default
+    Default,
+    /// Not set and required
+    Unset,
+    /// Set, but no variable is declared for it
+    Unused,
+    /// Neither declared nor set
+    Absent,
+}
+
+impl Cell {
+    pub fn label(self) -> &'static str {
+        match self {
+            Cell::Set => "set",
+            Cell::Default => "default",
+            Cell::Unset => "unset",
+            Cell::Unused => "unused",
+            Cell::Absent => "-",
+        }
+    }
+}
+
+/// Declared variables, then undeclared values, against environments.
+#[derive(Debug)]
+pub struct Matrix {
+    pub environments: Vec<String>,
+    pub rows: Vec<(String, Vec<Cell>)>,
+}
+
+pub fn matrix(module: &Module, environments: &[Environment]) -> Matrix {
+    let definitions = module.entries(EntryType::Definition);
+    let required = module.entries(EntryType::RequiredDefinition);
+    let values: Vec<_> = environments.iter().map(|e| e.values(module)).collect();
+    let is_set = |values: &[Variable], name: &str| values.iter().any(|v| v.name == name);
+
+    let mut names: Vec<String> = vec![];
+    for name in definitions
+        .iter()
+        .chain(values.iter().flatten())
+        .map(|v| &v.name)
+    {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+
+    let rows = names
+        .into_iter()
+        .map(|name| {
+            let declared = is_set(&definitions, &name);
+            let cells = values
+                .iter()
+                .map(|values| match (declared, is_set(values, &name)) {
+                    (true, true) => Cell::Set,
+                    (true, false) if is_set(&required, &name) => Cell::Unset,
+                    (true, false) => Cell::Default,
+                    (false, true) => Cell::Unused,
+                    (false, false) => Cell::Absent,
+                })
+                .collect();
+            (name, cells)
+        })
+        .collect();
+
+    Matrix {
+        environments: environments.iter().map(|e| e.name.clone()).collect(),
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!(
+            Environment::parse_spec("prod=envs/common.tfvars,envs/prod.tfvars"),
+            Ok((
+                "prod".to_string(),
+                vec![
+                    PathBuf::from("envs/common.tfvars"),
+                    PathBuf::from("envs/prod.tfvars")
+                ]
+            ))
+        );
+        assert!(Environment::parse_spec("prod").is_err());
+        assert!(Environment::parse_spec("=prod.tfvars").is_err());
+    }
+
+    #[test]
+    fn test_matrix() {
+        let dir = "tests/fixtures/environments";
+        let (module, _) = Module::load(Path::new(dir)).unwrap();
+        let environments: Vec<_> = ["prod", "staging"]
+            .iter()
+            .map(|name| {
+                let path = PathBuf::from(format!("{}/envs/{}.tfvars", dir, name));
+                Environment::load(name.to_string(), &[path]).0
+            })
+            .collect();
+        let matrix = matrix(&module, &environments);
+        assert_eq!(matrix.environments, vec!["prod", "staging"]);
+        let rows: Vec<_> = matrix
+            .rows
+            .iter()
+            .map(|(name, cells)| {
+                let labels: Vec<_> = cells.iter().map(|c| c.label()).collect();
+                format!("{} {}", name, labels.join(" "))
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                "region set set",
+                "instance_count set unset",
+                "instance_type set default",
+                "legacy_ami unused unused",
+                "debug - unused",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matrix_in_module_dir() {
+        // Var files next to main.tf that Terraform doesn't auto-load belong
+        // to their environment only
+        let dir = "tests/fixtures/environments_in_dir";
+        let (module, _) = Module::load(Path::new(dir)).unwrap();
+        let environments: Vec<_> = ["prod", "staging"]
+            .iter()
+            .map(|name| {
+                let path = PathBuf::from(format!("{}/{}.tfvars", dir, name));
+                Environment::load(name.to_string(), &[path]).0
+            })
+            .collect();
+        let matrix = matrix(&module, &environments);
+        let cells: Vec<_> = matrix
+            .rows
+            .iter()
+            .map(|(name, cells)| (name.as_str(), cells.clone()))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                ("region", vec![Cell::Set, Cell::Set]),
+                ("instance_count", vec![Cell::Set, Cell::Unset]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,1133 @@
+use std::fs;
+use std::path::Path;
+
+use glob::glob;
+use regex::Regex;
+
+use crate::hcl;
+use crate::types;
+
+lazy_static! {
+    static ref COMMENT_USE_REGEX: Regex = Regex::new(r#"\bvar\.([\w-]+)"#).unwrap();
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum EntryType {
+    Definition,
+    /// Definition without a `default`, which callers have to set
+    RequiredDefinition,
+    Use,
+    /// Attribute of an object-typed variable, named `var.attr`, nested
+    /// attributes as `var.attr.inner`
+    ObjectAttributeDefinition,
+    /// `var.x` reference with its full path of attributes, `x.attr.inner`
+    ObjectAttributeUse,
+    /// `var.x` mentioned in a comment
+    CommentUse,
+    /// Attribute of a `locals` block
+    LocalDefinition,
+    /// `local.x` reference
+    LocalUse,
+    /// `data "type" "name"` block, named `type.name`
+    DataDefinition,
+    /// `data.type.name` reference
+    DataUse,
+    /// `output` block
+    OutputDefinition,
+    /// `module.name.output` reference, named `name.output`. A reference to
+    /// the whole module (`module.name`) is named `name`.
+    ModuleOutputUse,
+    /// `provider` block with an `alias`, named `type.alias`
+    ProviderAliasDefinition,
+    /// `provider` argument of a resource or data source, or a value of the
+    /// `providers` map of a module call
+    ProviderAliasUse,
+    /// Entry of `terraform { required_providers { ... } }`, named by its
+    /// local name
+    RequiredProvider,
+    /// Provider local name that a resource or data source type starts
+    /// with, that a `provider` block configures or that a provider
+    /// reference names
+    ProviderUse,
+    /// Path read by `file()`, `templatefile()` and similar functions,
+    /// relative to the module directory. Parts that can't be resolved
+    /// statically are `*`, and for `fileset()` its path and pattern are
+    /// joined.
+    FileReference,
+    /// Path given to `fileexists()`. The path may be missing, and a file
+    /// reference to the same path is guarded by it.
+    FileExistenceCheck,
+    /// Top-level attribute of a .tfvars file. Keys nested in map or object
+    /// values are part of the value, not variables of their own.
+    Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub at: String,
+    pub span: hcl::Span,
+}
+
+/// Span of entries that don't come from a line of a file.
+pub fn no_position() -> hcl::Span {
+    let pos = hcl::Pos {
+        byte: 0,
+        line: 0,
+        column: 0,
+    };
+    hcl::Span {
+        start: pos,
+        end: pos,
+    }
+}
+
+impl Variable {
+    /// `path:line:col`, the way editors and CI systems expect it. Entries
+    /// without a line, such as environment variables, are just `at`.
+    pub fn location(&self) -> String {
+        if self.span.start.line == 0 {
+            return self.at.clone();
+        }
+        format!(
+            "{}:{}:{}",
+            self.at, self.span.start.line, self.span.start.column
+        )
+    }
+}
+
+/// A `module` block. The module name and location are kept in `call`.
+#[derive(Debug)]
+pub struct ModuleCall {
+    pub call: Variable,
+    pub source: Option<String>,
+    /// Input variables passed to the module, meta-arguments excluded
+    pub arguments: Vec<Variable>,
+}
+
+impl ModuleCall {
+    /// Whether `source` is a path on the local filesystem, as opposed to a
+    /// registry address or a remote URL.
+    pub fn is_local(&self) -> bool {
+        self.source
+            .as_ref()
+            .is_some_and(|s| s.starts_with("./") || s.starts_with("../"))
+    }
+}
+
+/// Functions that read the file at the path in their first argument.
+const FILE_FUNCTIONS: &[&str] = &[
+    "file",
+    "filebase64",
+    "filebase64sha256",
+    "filebase64sha512",
+    "filemd5",
+    "fileset",
+    "filesha1",
+    "filesha256",
+    "filesha512",
+    "templatefile",
+];
+
+/// A path argument as a glob pattern relative to the module directory.
+/// `path.module` resolves to the module directory and any other
+/// interpolation to `*`, `path.root` and `path.cwd` included, as does a
+/// path that isn't a template at all. Absolute paths give `None`.
+fn path_pattern(expr: &hcl::Expression) -> Option<String> {
+    let is_module_path = |e: &hcl::Expression| {
+        e.as_traversal()
+            .is_some_and(|t| t.root == "path" && t.attrs == ["module"])
+    };
+    if is_module_path(expr) {
+        return Some(String::new());
+    }
+    let parts = match &expr.kind {
+        hcl::ExprKind::Template(parts) => parts,
+        _ => return Some("*".to_string()),
+    };
+    let mut pattern = String::new();
+    for part in parts {
+        match part {
+            hcl::TemplatePart::Literal(text) => pattern.push_str(text),
+            hcl::TemplatePart::Interpolation(e) if is_module_path(e) => pattern.push('.'),
+            hcl::TemplatePart::Interpolation(_) => pattern.push('*'),
+            hcl::TemplatePart::Directive(_) => return None,
+        }
+    }
+    if pattern.starts_with('/') {
+        return None;
+    }
+    let pattern = pattern.trim_start_matches("./");
+    Some(if pattern == "." { "" } else { pattern }.to_string())
+}
+
+const MODULE_META_ARGUMENTS: &[&str] = &[
+    "source",
+    "version",
+    "count",
+    "for_each",
+    "providers",
+    "depends_on",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileType {
+    Source,
+    SourceJson,
+    Vars,
+    VarsJson,
+    /// `terragrunt.hcl`, which sets the variables of the module in its
+    /// `terraform { source = ... }` through `inputs`
+    Terragrunt,
+}
+
+impl FileType {
+    fn pattern(self) -> String {
+        match self {
+            FileType::Source => "*.tf".to_string(),
+            FileType::SourceJson => "*.tf.json".to_string(),
+            FileType::Vars => "*.tfvars".to_string(),
+            FileType::VarsJson => "*.tfvars.json".to_string(),
+            FileType::Terragrunt => "terragrunt.hcl".to_string(),
+        }
+    }
+
+    pub fn is_source(self) -> bool {
+        self == FileType::Source || self == FileType::SourceJson
+    }
+
+    pub fn is_vars(self) -> bool {
+        self == FileType::Vars || self == FileType::VarsJson
+    }
+}
+
+/// Number of labels of top-level blocks in Terraform configuration, used
+/// to tell blocks from attributes in the JSON syntax.
+fn block_labels(block_type: &str) -> Option<usize> {
+    match block_type {
+        "terraform" | "locals" => Some(0),
+        "variable" | "output" | "module" | "provider" => Some(1),
+        "resource" | "data" => Some(2),
+        _ => None,
+    }
+}
+
+/// `aws.east`, or `aws` for the default configuration, as a provider
+/// configuration reference, either bare or, in the JSON syntax, as a string.
+fn provider_reference(expr: &hcl::Expression) -> Option<String> {
+    match expr.as_traversal() {
+        Some(t) if t.attrs.is_empty() => Some(t.root),
+        Some(t) if t.attrs.len() == 1 => Some(format!("{}.{}", t.root, t.attrs[0])),
+        Some(_) => None,
+        None => expr.as_literal_string(),
+    }
+}
+
+#[derive(Debug)]
+pub struct File {
+    pub file_type: FileType,
+    pub path: String,
+    pub body: hcl::Body,
+    pub comments: Vec<hcl::Comment>,
+    pub contents: String,
+}
+
+/// A file found in a module directory, or the error reading it
+pub type Found = (FileType, Result<File, String>);
+
+impl File {
+    pub fn files_in(dir: &Path) -> Result<Vec<Found>, String> {
+        let mut files = Self::get_files(FileType::Source, dir)?;
+        files.extend(Self::get_files(FileType::SourceJson, dir)?);
+        files.extend(Self::get_files(FileType::Vars, dir)?);
+        files.extend(Self::get_files(FileType::VarsJson, dir)?);
+        files.extend(Self::get_files(FileType::Terragrunt, dir)?);
+        Ok(files)
+    }
+
+    fn get_files(file_type: FileType, dir: &Path) -> Result<Vec<Found>, String> {
+        let path_buf = dir.join(file_type.pattern());
+
+        let g = match path_buf.as_path().to_str() {
+            Some(glob_path) => glob_path.to_string(),
+            None => return Err("Failed to construct glob expression".to_string()),
+        };
+
+        let file_paths = match glob(&g) {
+            Ok(files) => files.filter_map(Result::ok),
+            Err(err) => return Err(err.to_string()),
+        };
+
+        let files = file_paths
+            .map(|path| (file_type, Self::read(file_type, &path)))
+            .collect();
+        Ok(files)
+    }
+
+    /// A var file given on the command line, which may live anywhere.
+    /// `.tfvars.json` files are read as JSON, anything else as native syntax.
+    pub fn read_var_file(path: &Path) -> Result<File, String> {
+        let is_json = path.to_str().is_some_and(|p| p.ends_with(".json"));
+        let file_type = if is_json {
+            FileType::VarsJson
+        } else {
+            FileType::Vars
+        };
+        Self::read(file_type, path)
+    }
+
+    fn read(file_type: FileType, path: &Path) -> Result<File, String> {
+        let path_str = path
+            .to_path_buf()
+            .into_os_string()
+            .into_string()
+            .unwrap_or_else(|_| "unknown path".to_string());
+        if let Ok(contents) = fs::read_to_string(path) {
+            Self::parse(file_type, path_str, contents)
+        } else {
+            Err(format!("Error: could not read file {}", path_str))
+        }
+    }
+
+    pub fn parse(file_type: FileType, path: String, contents: String) -> Result<File, String> {
+        let parsed = match file_type {
+            FileType::Source | FileType::Vars | FileType::Terragrunt => hcl::parse(&contents),
+            FileType::SourceJson => hcl::parse_json(&contents, &block_labels),
+            FileType::VarsJson => hcl::parse_json(&contents, &|_| None),
+        };
+        match parsed {
+            Ok(document) => Ok(File {
+                file_type,
+                path,
+                body: document.body,
+                comments: document.comments,
+                contents,
+            }),
+            Err(err) => Err(format!("Error: could not parse file {}:{}", path, err)),
+        }
+    }
+
+    pub fn get_var_entries(&self, entry_type: EntryType) -> Vec<Variable> {
+        let entries: Vec<(String, hcl::Span)> = match entry_type {
+            EntryType::Definition => self
+                .body
+                .blocks_of("variable")
+                .filter_map(|block| Some((block.labels.first()?.clone(), block.span)))
+                .collect(),
+            EntryType::RequiredDefinition => self
+                .body
+                .blocks_of("variable")
+                .filter(|block| block.body.attribute("default").is_none())
+                .filter_map(|block| Some((block.labels.first()?.clone(), block.span)))
+                .collect(),
+            EntryType::Use => self
+                .traversal_entries("var", 1)
+                .into_iter()
+                .filter(|(name, span)| !self.in_own_declaration(name, *span))
+                .collect(),
+            EntryType::ObjectAttributeDefinition => self
+                .body
+                .blocks_of("variable")
+                .filter_map(|block| Some((block.labels.first()?, block.body.attribute("type")?)))
+                .flat_map(|(name, ty)| {
+                    types::object_attributes(&ty.expr)
+                        .into_iter()
+                        .map(move |(path, span)| (format!("{}.{}", name, path), span))
+                })
+                .collect(),
+            EntryType::ObjectAttributeUse => self
+                .body
+                .traversals()
+                .into_iter()
+                .filter(|t| t.root == "var" && !t.attrs.is_empty())
+                .filter(|t| !self.in_own_declaration(&t.attrs[0], t.span))
+                .map(|t| (t.attrs.join("."), t.span))
+                .collect(),
+            EntryType::CommentUse => self
+                .comments
+                .iter()
+                .flat_map(|c| {
+                    COMMENT_USE_REGEX.captures_iter(&c.text).map(move |cap| {
+                        let m = cap.get(0).unwrap();
+                        let span = hcl::Span {
+                            start: c.span.start.advance(&c.text[..m.start()]),
+                            end: c.span.start.advance(&c.text[..m.end()]),
+                        };
+                        (cap[1].to_string(), span)
+                    })
+                })
+                .collect(),
+            EntryType::LocalDefinition => self
+                .body
+                .blocks_of("locals")
+                .flat_map(|block| block.body.attributes())
+                .map(|a| (a.name.clone(), a.span))
+                .collect(),
+            EntryType::LocalUse => self.traversal_entries("local", 1),
+            EntryType::DataDefinition => self
+                .body
+                .blocks_of("data")
+                .filter(|block| block.labels.len() == 2)
+                .map(|block| (block.labels.join("."), block.span))
+                .collect(),
+            EntryType::DataUse => self.traversal_entries("data", 2),
+            EntryType::OutputDefinition => self
+                .body
+                .blocks_of("output")
+                .filter_map(|block| Some((block.labels.first()?.clone(), block.span)))
+                .collect(),
+            EntryType::ModuleOutputUse => self
+                .body
+                .traversals()
+                .into_iter()
+                .filter(|t| t.root == "module" && !t.attrs.is_empty())
+                .map(|t| {
+                    // Skip the instance key of a module with `count` or
+                    // `for_each`, as in `module.net["a"].vpc_id`
+                    let skip = if t.indexed.get(1) == Some(&true) {
+                        2
+                    } else {
+                        1
+                    };
+                    let name = match t.attrs.get(skip) {
+                        Some(output) => format!("{}.{}", t.attrs[0], output),
+                        None => t.attrs[0].clone(),
+                    };
+                    (name, t.span)
+                })
+                .collect(),
+            EntryType::ProviderAliasDefinition => self
+                .body
+                .blocks_of("provider")
+                .filter_map(|block| {
+                    let alias = block.body.attribute("alias")?.expr.as_literal_string()?;
+                    Some((format!("{}.{}", block.labels.first()?, alias), block.span))
+                })
+                .collect(),
+            EntryType::ProviderAliasUse => {
+                let resources = self
+                    .body
+                    .blocks_of("resource")
+                    .chain(self.body.blocks_of("data"))
+                    .filter_map(|block| block.body.attribute("provider"))
+                    .map(|a| &a.expr);
+                let module_providers = self
+                    .body
+                    .blocks_of("module")
+                    .filter_map(|block| block.body.attribute("providers"))
+                    .flat_map(|a| match &a.expr.kind {
+                        hcl::ExprKind::Object(items) => items.iter().map(|i| &i.value).collect(),
+                        _ => vec![],
+                    });
+                resources
+                    .chain(module_providers)
+                    .filter_map(|expr| Some((provider_reference(expr)?, expr.span)))
+                    .collect()
+            }
+            EntryType::RequiredProvider => self
+                .body
+                .blocks_of("terraform")
+                .flat_map(|block| {
+                    // A nested block in the native syntax, an object
+                    // attribute in the JSON one
+                    let native = block
+                        .body
+                        .blocks_of("required_providers")
+                        .flat_map(|b| b.body.attributes())
+                        .map(|a| (a.name.clone(), a.span));
+                    let json = block
+                        .body
+                        .attribute("required_providers")
+                        .into_iter()
+                        .flat_map(|a| match &a.expr.kind {
+                            hcl::ExprKind::Object(items) => items
+                                .iter()
+                                .filter_map(|i| Some((i.key.as_key()?, i.key.span)))
+                                .collect(),
+                            _ => vec![],
+                        });
+                    native.chain(json).collect::<Vec<_>>()
+                })
+                .collect(),
+            EntryType::ProviderUse => {
+                let resource_types = self
+                    .body
+                    .blocks_of("resource")
+                    .chain(self.body.blocks_of("data"))
+                    .filter_map(|block| {
+                        let provider = block.labels.first()?.split('_').next()?;
+                        Some((provider.to_string(), block.span))
+                    });
+                let configurations = self
+                    .body
+                    .blocks_of("provider")
+                    .filter_map(|block| Some((block.labels.first()?.clone(), block.span)));
+                let references = self
+                    .get_var_entries(EntryType::ProviderAliasUse)
+                    .into_iter()
+                    .map(|alias| {
+                        let provider = alias.name.split('.').next().unwrap_or_default();
+                        (provider.to_string(), alias.span)
+                    });
+                resource_types
+                    .chain(configurations)
+                    .chain(references)
+                    .collect()
+            }
+            EntryType::FileReference => self.path_arguments(FILE_FUNCTIONS),
+            EntryType::FileExistenceCheck => self.path_arguments(&["fileexists"]),
+            EntryType::Value => self
+                .body
+                .attributes()
+                .map(|a| (a.name.clone(), a.span))
+                .collect(),
+        };
+
+        entries
+            .into_iter()
+            .map(|(name, span)| self.variable(name, span))
+            .collect()
+    }
+
+    /// Path patterns given to calls of any of `functions`.
+    fn path_arguments(&self, functions: &[&str]) -> Vec<(String, hcl::Span)> {
+        let mut references = vec![];
+        for expr in self.body.expressions() {
+            expr.walk(&mut |e| {
+                if let hcl::ExprKind::FunctionCall { name, args, .. } = &e.kind {
+                    if !functions.contains(&name.as_str()) {
+                        return;
+                    }
+                    let path = args.first().and_then(path_pattern);
+                    let pattern = match (name.as_str(), path) {
+                        ("fileset", Some(path)) => args
+                            .get(1)
+                            .and_then(path_pattern)
+                            .map(|p| format!("{}/{}", path, p)),
+                        (_, path) => path,
+                    };
+                    if let Some(pattern) = pattern {
+                        let pattern = pattern.trim_start_matches('/').to_string();
+                        references.push((pattern, e.span));
+                    }
+                }
+            });
+        }
+        references
+    }
+
+    /// The first `depth` attributes of every traversal starting at `root`,
+    /// such as `region` in `var.region.name` for a depth of 1.
+    fn traversal_entries(&self, root: &str, depth: usize) -> Vec<(String, hcl::Span)> {
+        self.body
+            .traversals()
+            .into_iter()
+            .filter(|t| t.root == root && t.attrs.len() >= depth)
+            .map(|t| (t.attrs[..depth].join("."), t.span))
+            .collect()
+    }
+
+    /// Whether `span` lies within the `variable` block declaring `name`,
+    /// such as a `validation` condition checking the variable itself.
+    fn in_own_declaration(&self, name: &str, span: hcl::Span) -> bool {
+        self.body.blocks_of("variable").any(|block| {
+            block.labels.first().map(String::as_str) == Some(name)
+                && block.span.start.byte <= span.start.byte
+                && span.end.byte <= block.span.end.byte
+        })
+    }
+
+    pub fn module_calls(&self) -> Vec<ModuleCall> {
+        self.body
+            .blocks_of("module")
+            .filter_map(|block| {
+                let name = block.labels.first()?.clone();
+                let source = block
+                    .body
+                    .attribute("source")
+                    .and_then(|a| a.expr.as_literal_string());
+                let arguments = block
+                    .body
+                    .attributes()
+                    .filter(|a| !MODULE_META_ARGUMENTS.contains(&a.name.as_str()))
+                    .map(|a| self.variable(a.name.clone(), a.span))
+                    .collect();
+                Some(ModuleCall {
+                    call: self.variable(name, block.span),
+                    source,
+                    arguments,
+                })
+            })
+            .collect()
+    }
+
+    /// The module a `terragrunt.hcl` deploys, as a call with its `inputs`
+    /// as arguments. Inputs that are not an object literal, such as a
+    /// `merge(...)`, can't be checked and give no call.
+    pub fn terragrunt_call(&self) -> Option<ModuleCall> {
+        if self.file_type != FileType::Terragrunt {
+            return None;
+        }
+        let terraform = self.body.blocks_of("terraform").next()?;
+        let source = terraform
+            .body
+            .attribute("source")
+            .and_then(|a| a.expr.as_literal_string());
+        let arguments = match self.body.attribute("inputs") {
+            Some(inputs) => match &inputs.expr.kind {
+                hcl::ExprKind::Object(items) => items
+                    .iter()
+                    .filter_map(|i| Some(self.variable(i.key.as_key()?, i.key.span)))
+                    .collect(),
+                _ => return None,
+            },
+            None => vec![],
+        };
+        Some(ModuleCall {
+            call: self.variable("terraform".to_string(), terraform.span),
+            source,
+            arguments,
+        })
+    }
+
+    /// Whether Terraform loads this var file without a `-var-file` flag
+    /// when it sits in the module directory: `terraform.tfvars`,
+    /// `terraform.tfvars.json` and `*.auto.tfvars(.json)`.
+    pub fn is_auto_loaded(&self) -> bool {
+        self.auto_load_order().is_some()
+    }
+
+    /// Sort key of an auto-loaded var file. Terraform loads
+    /// `terraform.tfvars`, then `terraform.tfvars.json`, then the
+    /// `*.auto.tfvars(.json)` files in lexical order of their names, and
+    /// later values override earlier ones.
+    pub fn auto_load_order(&self) -> Option<(u8, String)> {
+        let name = Path::new(&self.path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("");
+        if !self.file_type.is_vars() {
+            return None;
+        }
+        match name {
+            "terraform.tfvars" => Some((0, String::new())),
+            "terraform.tfvars.json" => Some((1, String::new())),
+            _ if name.ends_with(".auto.tfvars") || name.ends_with(".auto.tfvars.json") => {
+                Some((2, name.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Source text of the value this var file sets for `name`, on one line.
+    pub fn value_text(&self, name: &str) -> Option<String> {
+        let value = self.body.attributes().filter(|a| a.name == name).last()?;
+        let text = self
+            .contents
+            .get(value.expr.span.start.byte..value.expr.span.end.byte)?;
+        Some(match text.lines().next() {
+            Some(first) if first.len() < text.len() => format!("{} ...", first),
+            _ => text.to_string(),
+        })
+    }
+
+    fn variable(&self, name: String, span: hcl::Span) -> Variable {
+        Variable {
+            name,
+            at: self.path.clone(),
+            span,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(file_type: FileType, contents: &str, entry_type: EntryType) -> Vec<String> {
+        File::parse(file_type, "test".to_string(), contents.to_string())
+            .unwrap()
+            .get_var_entries(entry_type)
+            .into_iter()
+            .map(|v| v.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_tfvars_values() {
+        let test_string = r#"
+        one = 42
+        two = "booooring"
+    two_and_a_half = "now it's getting interesting"
+
+          three_times_fourty_two = true
+        "#;
+        assert_eq!(
+            names(FileType::Vars, test_string, EntryType::Value),
+            vec!["one", "two", "two_and_a_half", "three_times_fourty_two"]
+        );
+    }
+
+    #[test]
+    fn test_variable_definitions() {
+        let test_string = r#"
+        variable "surprisingly_important_variable" {
+            default = 42
+        }
+        "#;
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::Definition),
+            vec!["surprisingly_important_variable"]
+        );
+    }
+
+    #[test]
+    fn test_required_definitions() {
+        let test_string = r#"
+        variable "optional" {
+            default = null
+        }
+        variable "required" {
+            type = string
+        }
+        "#;
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::RequiredDefinition),
+            vec!["required"]
+        );
+    }
+
+    #[test]
+    fn test_module_calls() {
+        let test_string = r#"
+        module "net" {
+            source   = "./modules/net"
+            count    = 2
+            cidr     = "10.0.0.0/16"
+            providers = { aws = aws.east }
+        }
+        module "vpc" {
+            source = "terraform-aws-modules/vpc/aws"
+        }
+        "#;
+        let calls = File::parse(
+            FileType::Source,
+            "test".to_string(),
+            test_string.to_string(),
+        )
+        .unwrap()
+        .module_calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].call.name, "net");
+        assert!(calls[0].is_local());
+        let arguments: Vec<_> = calls[0].arguments.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(arguments, vec!["cidr"]);
+        assert!(!calls[1].is_local());
+    }
+
+    #[test]
+    fn test_locals() {
+        let test_string = r#"
+        locals {
+            prefix = "app"
+            name   = "${local.prefix}-${var.env}"
+        }
+        locals {
+            tags = { Name = local.name }
+        }
+        "#;
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::LocalDefinition),
+            vec!["prefix", "name", "tags"]
+        );
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::LocalUse),
+            vec!["prefix", "name"]
+        );
+    }
+
+    #[test]
+    fn test_data_sources() {
+        let test_string = r#"
+        data "aws_ami" "ubuntu" {
+            most_recent = true
+        }
+        resource "aws_instance" "web" {
+            ami   = data.aws_ami.ubuntu.id
+            count = length(data.aws_availability_zones.all.names)
+        }
+        "#;
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::DataDefinition),
+            vec!["aws_ami.ubuntu"]
+        );
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::DataUse),
+            vec!["aws_ami.ubuntu", "aws_availability_zones.all"]
+        );
+    }
+
+    #[test]
+    fn test_outputs() {
+        let test_string = r#"
+        output "vpc_id" {
+            value = module.net.vpc_id
+        }
+        output "subnets" {
+            value = [for s in module.net[0].subnets : s.id]
+        }
+        output "zones" {
+            value = module.zones["eu"].zone_id
+        }
+        "#;
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::OutputDefinition),
+            vec!["vpc_id", "subnets", "zones"]
+        );
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::ModuleOutputUse),
+            vec!["net.vpc_id", "net", "zones.zone_id"]
+        );
+    }
+
+    #[test]
+    fn test_provider_aliases() {
+        let test_string = r#"
+        provider "aws" {
+            region = "eu-west-1"
+        }
+        provider "aws" {
+            alias  = "us_east_1"
+            region = "us-east-1"
+        }
+        resource "aws_acm_certificate" "cdn" {
+            provider = aws.us_east_1
+        }
+        module "dns" {
+            source    = "./dns"
+            providers = { aws = aws.dns, "aws.other" = aws }
+        }
+        "#;
+        assert_eq!(
+            names(
+                FileType::Source,
+                test_string,
+                EntryType::ProviderAliasDefinition
+            ),
+            vec!["aws.us_east_1"]
+        );
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::ProviderAliasUse),
+            vec!["aws.us_east_1", "aws.dns", "aws"]
+        );
+    }
+
+    #[test]
+    fn test_required_providers() {
+        let test_string = r#"
+        terraform {
+            required_providers {
+                aws    = { source = "hashicorp/aws" }
+                random = { source = "hashicorp/random" }
+            }
+        }
+        provider "aws" {}
+        data "http" "ip" {}
+        "#;
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::RequiredProvider),
+            vec!["aws", "random"]
+        );
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::ProviderUse),
+            vec!["http", "aws"]
+        );
+
+        let json = r#"{ "terraform": { "required_providers": { "aws": {} } } }"#;
+        assert_eq!(
+            names(FileType::SourceJson, json, EntryType::RequiredProvider),
+            vec!["aws"]
+        );
+    }
+
+    #[test]
+    fn test_terragrunt_call() {
+        let test_string = r#"
+        include "root" {
+            path = find_in_parent_folders()
+        }
+        terraform {
+            source = "../../modules//vpc"
+        }
+        inputs = {
+            cidr = "10.0.0.0/16"
+            "name" = "main"
+        }
+        "#;
+        let file = File::parse(
+            FileType::Terragrunt,
+            "terragrunt.hcl".to_string(),
+            test_string.to_string(),
+        )
+        .unwrap();
+        let call = file.terragrunt_call().unwrap();
+        assert_eq!(call.source.as_deref(), Some("../../modules//vpc"));
+        assert!(call.is_local());
+        let arguments: Vec<_> = call.arguments.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(arguments, vec!["cidr", "name"]);
+    }
+
+    #[test]
+    fn test_file_references() {
+        let test_string = r#"
+        locals {
+            policy  = file("${path.module}/policies/s3.json")
+            script  = filebase64("scripts/init.sh")
+            user    = templatefile("${path.module}/templates/${var.os}.tftpl", {})
+            configs = fileset(path.module, "configs/*.yaml")
+            other   = file(var.path)
+            abs     = file("/etc/hosts")
+            extra   = fileexists("extra.json") ? file("extra.json") : "{}"
+            root    = file("${path.root}/policies/s3.json")
+            cwd     = fileset(path.cwd, "*.json")
+        }
+        "#;
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::FileReference),
+            vec![
+                "policies/s3.json",
+                "scripts/init.sh",
+                "templates/*.tftpl",
+                "configs/*.yaml",
+                "*",
+                "extra.json",
+                "*/policies/s3.json",
+                "*/*.json"
+            ]
+        );
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::FileExistenceCheck),
+            vec!["extra.json"]
+        );
+    }
+
+    #[test]
+    fn test_auto_loaded() {
+        let auto_loaded = |path: &str| {
+            let file_type = if path.ends_with(".json") {
+                FileType::VarsJson
+            } else {
+                FileType::Vars
+            };
+            File {
+                file_type,
+                path: path.to_string(),
+                body: hcl::Body::default(),
+                comments: vec![],
+                contents: String::new(),
+            }
+            .is_auto_loaded()
+        };
+        assert!(auto_loaded("env/terraform.tfvars"));
+        assert!(auto_loaded("terraform.tfvars.json"));
+        assert!(auto_loaded("env/prod.auto.tfvars"));
+        assert!(auto_loaded("prod.auto.tfvars.json"));
+        assert!(!auto_loaded("prod.tfvars"));
+        assert!(!auto_loaded("terraform.tfvars.bak"));
+    }
+
+    #[test]
+    fn test_value_text() {
+        let file = File::parse(
+            FileType::Vars,
+            "terraform.tfvars".to_string(),
+            "zone = \"eu-west-1a\"\ntags = {\n  team = \"x\"\n}\n".to_string(),
+        )
+        .unwrap();
+        assert_eq!(file.value_text("zone").as_deref(), Some("\"eu-west-1a\""));
+        assert_eq!(file.value_text("tags").as_deref(), Some("{ ..."));
+        assert_eq!(file.value_text("region"), None);
+    }
+
+    #[test]
+    fn test_variable_uses() {
+        let test_string = r#"something = "${foo(var.very_important_variable)}""#;
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::Use),
+            vec!["very_important_variable"]
+        );
+    }
+
+    #[test]
+    fn test_object_attributes() {
+        let test_string = r#"
+        variable "settings" {
+            type = object({ a = string, b = optional(number, 1) })
+        }
+        resource "x" "y" {
+            a   = var.settings.a
+            all = jsonencode(var.settings)
+            z   = var.zones[0].name
+        }
+        "#;
+        assert_eq!(
+            names(
+                FileType::Source,
+                test_string,
+                EntryType::ObjectAttributeDefinition
+            ),
+            vec!["settings.a", "settings.b"]
+        );
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::ObjectAttributeUse),
+            vec!["settings.a", "settings", "zones"]
+        );
+    }
+
+    #[test]
+    fn test_self_references_are_not_uses() {
+        let test_string = r#"
+        variable "name" {
+            type = string
+            validation {
+                condition     = length(var.name) > 0
+                error_message = "Name can't be empty."
+            }
+        }
+        variable "suffix" {
+            default = null
+            validation {
+                condition     = var.suffix == null || var.name != ""
+                error_message = "Suffix needs a name."
+            }
+        }
+        "#;
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::Use),
+            vec!["name"]
+        );
+    }
+
+    #[test]
+    fn test_comments_are_not_uses() {
+        let test_string = r#"
+        # count = var.legacy_flag
+        name = "x" // see var.foo
+        /* var.bar
+           var.baz-qux */
+        # set the envvar.legacy to 1
+        "#;
+        assert!(names(FileType::Source, test_string, EntryType::Use).is_empty());
+        assert_eq!(
+            names(FileType::Source, test_string, EntryType::CommentUse),
+            vec!["legacy_flag", "foo", "bar", "baz-qux"]
+        );
+        assert!(names(FileType::Vars, "# region = \"eu-west-1\"", EntryType::Value).is_empty());
+    }
+
+    fn locations(file_type: FileType, contents: &str, entry_type: EntryType) -> Vec<String> {
+        File::parse(file_type, "test.tf".to_string(), contents.to_string())
+            .unwrap()
+            .get_var_entries(entry_type)
+            .iter()
+            .map(Variable::location)
+            .collect()
+    }
+
+    #[test]
+    fn test_entry_locations() {
+        let test_string = r#"
+variable "region" {}
+
+  # see var.legacy
+resource "x" "y" {
+  name = "${var.region}"
+}
+"#;
+        assert_eq!(
+            locations(FileType::Source, test_string, EntryType::Definition),
+            vec!["test.tf:2:1"]
+        );
+        assert_eq!(
+            locations(FileType::Source, test_string, EntryType::Use),
+            vec!["test.tf:6:13"]
+        );
+        assert_eq!(
+            locations(FileType::Source, test_string, EntryType::CommentUse),
+            vec!["test.tf:4:9"]
+        );
+        assert_eq!(
+            locations(FileType::Vars, "\n  region = 1\n", EntryType::Value),
+            vec!["test.tf:2:3"]
+        );
+
+        let entry = &File::parse(
+            FileType::Source,
+            "test.tf".to_string(),
+            test_string.to_string(),
+        )
+        .unwrap()
+        .get_var_entries(EntryType::Use)[0];
+        assert_eq!(
+            &test_string[entry.span.start.byte..entry.span.end.byte],
+            "var.region"
+        );
+    }
+
+    #[test]
+    fn test_only_top_level_tfvars_values() {
+        let test_string = r#"
+        tags = { owner = "x", team = "y" }
+        subnets = [
+          { name = "a", cidr = "10.0.0.0/24" },
+          { name = "b", cidr = "10.0.1.0/24" },
+        ]
+        settings = {
+          nested = {
+            deep = true
+          }
+        }
+        policy = <<EOT
+        not_a_value = 1
+        EOT
+        region = "eu-west-1"
+        "#;
+        assert_eq!(
+            names(FileType::Vars, test_string, EntryType::Value),
+            vec!["tags", "subnets", "settings", "policy", "region"]
+        );
+    }
+
+    #[test]
+    fn test_json_files() {
+        let source = r#"{
+          "variable": { "ami": {}, "region": { "default": "eu-west-1" } },
+          "resource": {
+            "aws_instance": { "web": { "ami": "${var.ami}", "note": "var.region" } }
+          }
+        }"#;
+        assert_eq!(
+            names(FileType::SourceJson, source, EntryType::Definition),
+            vec!["ami", "region"]
+        );
+        assert_eq!(
+            names(FileType::SourceJson, source, EntryType::Use),
+            vec!["ami"]
+        );
+
+        let vars = r#"{ "ami": "ami-123", "tags": { "owner": "x" } }"#;
+        assert_eq!(
+            names(FileType::VarsJson, vars, EntryType::Value),
+            vec!["ami", "tags"]
+        );
+    }
+
+    #[test]
+    fn test_variable_lookalikes_are_not_uses() {
+        let test_string = r#"
+        description = "set var.region to override"
+        value       = other.var.region
+        "#;
+        assert!(names(FileType::Source, test_string, EntryType::Use).is_empty());
+    }
+}
@@ -0,0 +1,955 @@
+use std::fs;
+use std::path::Path;
+
+use glob::Pattern;
+
+use crate::environment::Environment;
+use crate::file::{no_position, EntryType, ModuleCall, Variable};
+use crate::module::{is_module_dir, Module};
+use crate::types::Type;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    /// Configuration Terraform itself would reject
+    Error,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FindingKind {
+    UnusedDefinition,
+    ReferencedInCommentsOnly,
+    UnusedValue,
+    /// Value for an undeclared variable that only some environments set
+    UnusedValueInSomeEnvironments,
+    RequiredNotSet,
+    UnusedObjectAttribute,
+    UnusedLocal,
+    UnusedDataSource,
+    UndeclaredVariable,
+    DuplicateDefinition,
+    DuplicateValue,
+    /// Value of a var file that one loaded later overrides
+    ShadowedValue,
+    UndeclaredModuleArgument,
+    MissingModuleArgument,
+    ModuleSourceNotFound,
+    InvalidTypeConstraint,
+    TypeMismatch,
+    UnusedOutput,
+    UnusedProviderAlias,
+    UnusedRequiredProvider,
+    UndeclaredInput,
+    MissingInput,
+    UnusedFile,
+    FileNotFound,
+}
+
+impl FindingKind {
+    pub fn prefix(self) -> &'static str {
+        match self {
+            FindingKind::UnusedDefinition => "Unused definition",
+            FindingKind::ReferencedInCommentsOnly => "Referenced only in comments",
+            FindingKind::UnusedValue => "Unused value for",
+            FindingKind::UnusedValueInSomeEnvironments => "Unused value in some environments for",
+            FindingKind::RequiredNotSet => "No value set for required variable",
+            FindingKind::UnusedObjectAttribute => "Unused object attribute",
+            FindingKind::UnusedLocal => "Unused local",
+            FindingKind::UnusedDataSource => "Unused data source",
+            FindingKind::UndeclaredVariable => "Undeclared variable",
+            FindingKind::DuplicateDefinition => "Duplicate declaration of variable",
+            FindingKind::DuplicateValue => "Duplicate value for",
+            FindingKind::ShadowedValue => "Value overridden by a later var file for",
+            FindingKind::UndeclaredModuleArgument => "Undeclared module argument",
+            FindingKind::MissingModuleArgument => "Missing required module argument",
+            FindingKind::ModuleSourceNotFound => "Module source not found for",
+            FindingKind::InvalidTypeConstraint => "Invalid type constraint of variable",
+            FindingKind::TypeMismatch => "Value does not match the type of",
+            FindingKind::UnusedOutput => "Unused output",
+            FindingKind::UnusedProviderAlias => "Unused provider alias",
+            FindingKind::UnusedRequiredProvider => "Unused required provider",
+            FindingKind::UndeclaredInput => "Undeclared Terragrunt input",
+            FindingKind::MissingInput => "No Terragrunt input for required variable",
+            FindingKind::UnusedFile => "Unused file",
+            FindingKind::FileNotFound => "File not found",
+        }
+    }
+
+    pub fn severity(self) -> Severity {
+        match self {
+            FindingKind::UndeclaredVariable
+            | FindingKind::DuplicateDefinition
+            | FindingKind::DuplicateValue
+            | FindingKind::UndeclaredModuleArgument
+            | FindingKind::MissingModuleArgument
+            | FindingKind::ModuleSourceNotFound
+            | FindingKind::UndeclaredInput
+            | FindingKind::MissingInput
+            | FindingKind::FileNotFound
+            | FindingKind::InvalidTypeConstraint
+            | FindingKind::TypeMismatch => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub var: Variable,
+    /// Extra context printed after the name
+    pub note: Option<String>,
+}
+
+impl Finding {
+    fn new(kind: FindingKind, var: &Variable) -> Finding {
+        Finding {
+            kind,
+            var: var.clone(),
+            note: None,
+        }
+    }
+
+    fn with_note(mut self, note: String) -> Finding {
+        self.note = Some(note);
+        self
+    }
+}
+
+/// Values that don't match any of the definitions. With the arguments
+/// swapped, definitions that none of the uses refer to.
+fn undeclared<'a>(values: &'a [Variable], definitions: &[Variable]) -> Vec<&'a Variable> {
+    values
+        .iter()
+        .filter(|val| {
+            definitions
+                .iter()
+                .find(|def| def.name == val.name)
+                .is_none()
+        })
+        .collect()
+}
+
+/// Definitions that none of the values set.
+fn unset<'a>(definitions: &'a [Variable], values: &[Variable]) -> Vec<&'a Variable> {
+    definitions
+        .iter()
+        .filter(|def| !values.iter().any(|val| val.name == def.name))
+        .collect()
+}
+
+/// Entries whose name occurs again, each one noted with the locations of
+/// the others.
+fn duplicates(kind: FindingKind, entries: &[Variable]) -> Vec<Finding> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let others: Vec<_> = entries
+                .iter()
+                .filter(|e| e.name == entry.name && !(e.at == entry.at && e.span == entry.span))
+                .map(Variable::location)
+                .collect();
+            if others.is_empty() {
+                None
+            } else {
+                let note = format!("also at {}", others.join(", "));
+                Some(Finding::new(kind, entry).with_note(note))
+            }
+        })
+        .collect()
+}
+
+pub fn analyse(module: &Module) -> Vec<Finding> {
+    let definitions = module.entries(EntryType::Definition);
+    let uses = module.entries(EntryType::Use);
+    let comment_uses = module.entries(EntryType::CommentUse);
+    let values = module.entries(EntryType::Value);
+
+    let unused = definitions
+        .iter()
+        .filter(|def| uses.iter().find(|inst| inst.name == def.name).is_none())
+        .map(|def| {
+            let in_comments = comment_uses.iter().any(|c| c.name == def.name);
+            let kind = if in_comments {
+                FindingKind::ReferencedInCommentsOnly
+            } else {
+                FindingKind::UnusedDefinition
+            };
+            Finding::new(kind, def)
+        });
+
+    let unused_vals = undeclared(&values, &definitions)
+        .into_iter()
+        .map(|val| Finding::new(FindingKind::UnusedValue, val));
+
+    // Without any var files, this is most likely a child module that gets
+    // its values from module calls
+    let has_var_files = module.has_values();
+    let required = module.entries(EntryType::RequiredDefinition);
+    let required_not_set = unset(&required, &values)
+        .into_iter()
+        .filter(|_| has_var_files)
+        .map(|def| Finding::new(FindingKind::RequiredNotSet, def));
+
+    // An attribute counts as read along with the object holding it, or
+    // with any attribute nested in it
+    let attribute_uses = module.entries(EntryType::ObjectAttributeUse);
+    let unused_attributes = module
+        .entries(EntryType::ObjectAttributeDefinition)
+        .into_iter()
+        .filter(|attr| {
+            let variable = attr.name.split('.').next().unwrap_or_default();
+            uses.iter().any(|u| u.name == variable)
+                && !attribute_uses.iter().any(|u| {
+                    u.name == attr.name
+                        || attr.name.starts_with(&format!("{}.", u.name))
+                        || u.name.starts_with(&format!("{}.", attr.name))
+                })
+        })
+        .map(|attr| Finding::new(FindingKind::UnusedObjectAttribute, &attr));
+
+    let local_definitions = module.entries(EntryType::LocalDefinition);
+    let local_uses = module.entries(EntryType::LocalUse);
+    let unused_locals = undeclared(&local_definitions, &local_uses)
+        .into_iter()
+        .map(|local| Finding::new(FindingKind::UnusedLocal, local));
+
+    let data_definitions = module.entries(EntryType::DataDefinition);
+    let data_uses = module.entries(EntryType::DataUse);
+    let unused_data = undeclared(&data_definitions, &data_uses)
+        .into_iter()
+        .map(|data| Finding::new(FindingKind::UnusedDataSource, data));
+
+    let alias_definitions = module.entries(EntryType::ProviderAliasDefinition);
+    let alias_uses = module.entries(EntryType::ProviderAliasUse);
+    let unused_aliases = undeclared(&alias_definitions, &alias_uses)
+        .into_iter()
+        .map(|alias| Finding::new(FindingKind::UnusedProviderAlias, alias));
+
+    let required_providers = module.entries(EntryType::RequiredProvider);
+    let provider_uses = module.entries(EntryType::ProviderUse);
+    let unused_providers = undeclared(&required_providers, &provider_uses)
+        .into_iter()
+        .map(|provider| Finding::new(FindingKind::UnusedRequiredProvider, provider));
+
+    let undeclared_uses = undeclared(&uses, &definitions)
+        .into_iter()
+        .map(|var_use| Finding::new(FindingKind::UndeclaredVariable, var_use));
+
+    // The uses and definitions in a source file that failed to parse are
+    // missing, nothing can be called unused or undeclared then
+    let mut findings: Vec<_> = if module.incomplete {
+        required_not_set.collect()
+    } else {
+        unused
+            .chain(unused_vals)
+            .chain(required_not_set)
+            .chain(undeclared_uses)
+            .chain(unused_attributes)
+            .chain(unused_locals)
+            .chain(unused_data)
+            .chain(unused_aliases)
+            .chain(unused_providers)
+            .collect()
+    };
+
+    findings.extend(duplicates(FindingKind::DuplicateDefinition, &definitions));
+    for file in module.files.iter().filter(|f| f.file_type.is_vars()) {
+        let values = file.get_var_entries(EntryType::Value);
+        findings.extend(duplicates(FindingKind::DuplicateValue, &values));
+    }
+    // Terraform merges var files, a key in a later file overrides the
+    // earlier ones rather than being rejected
+    let loaded = module.loaded_var_files();
+    for (i, file) in loaded.iter().enumerate() {
+        for value in file.get_var_entries(EntryType::Value) {
+            let winner = loaded[i + 1..].iter().rev().find_map(|later| {
+                let entries = later.get_var_entries(EntryType::Value);
+                let entry = entries.into_iter().rev().find(|v| v.name == value.name)?;
+                Some((later, entry))
+            });
+            if let Some((later, entry)) = winner {
+                let note = format!(
+                    "{} sets {}",
+                    entry.location(),
+                    later.value_text(&entry.name).unwrap_or_default()
+                );
+                findings.push(Finding::new(FindingKind::ShadowedValue, &value).with_note(note));
+            }
+        }
+    }
+
+    findings.extend(check_types(module));
+    findings.extend(check_files(module));
+
+    for call in module.module_calls().iter().filter(|c| c.is_local()) {
+        findings.extend(check_module_call(module, call));
+    }
+    for file in module.files.iter() {
+        if let Some(call) = file.terragrunt_call().filter(ModuleCall::is_local) {
+            // Inputs from included configurations are merged in, so any
+            // required variable may be set there
+            let includes = file.body.blocks_of("include").next().is_some();
+            let missing = Some(FindingKind::MissingInput).filter(|_| !includes);
+            findings.extend(check_call(
+                module,
+                &call,
+                call.source.clone().unwrap_or_default(),
+                FindingKind::UndeclaredInput,
+                missing,
+            ));
+        }
+    }
+    findings
+}
+
+/// The value checks of `analyse`, run for each environment separately
+/// instead of on all var files pooled.
+pub fn analyse_environments(module: &Module, environments: &[Environment]) -> Vec<Finding> {
+    let definitions = module.entries(EntryType::Definition);
+    let required = module.entries(EntryType::RequiredDefinition);
+    let values: Vec<_> = environments.iter().map(|e| e.values(module)).collect();
+    let set_in = |name: &str, set: bool| -> Vec<&str> {
+        environments
+            .iter()
+            .zip(&values)
+            .filter(|(_, values)| values.iter().any(|v| v.name == name) == set)
+            .map(|(e, _)| e.name.as_str())
+            .collect()
+    };
+
+    let mut findings = vec![];
+    // Values of the module's own var files are in every environment, but
+    // are reported once
+    let all_values = values.concat();
+    let mut reported = vec![];
+    for val in undeclared(&all_values, &definitions) {
+        if module.incomplete || reported.contains(&val.location()) {
+            continue;
+        }
+        reported.push(val.location());
+        let environments_set = set_in(&val.name, true);
+        let finding = if environments_set.len() == environments.len() {
+            Finding::new(FindingKind::UnusedValue, val)
+                .with_note("in every environment".to_string())
+        } else {
+            Finding::new(FindingKind::UnusedValueInSomeEnvironments, val)
+                .with_note(format!("set in {}", environments_set.join(", ")))
+        };
+        findings.push(finding);
+    }
+
+    for def in &required {
+        let environments_unset = set_in(&def.name, false);
+        if !environments_unset.is_empty() {
+            findings.push(
+                Finding::new(FindingKind::RequiredNotSet, def)
+                    .with_note(format!("in {}", environments_unset.join(", "))),
+            );
+        }
+    }
+    findings
+}
+
+/// Outputs of `module` that none of the modules in `tree` calling it read.
+/// Outputs of modules nobody in the tree calls are root outputs and are
+/// left alone.
+pub fn unused_outputs(module: &Module, tree: &[Module]) -> Vec<Finding> {
+    let dir = match fs::canonicalize(&module.dir) {
+        Ok(dir) => dir,
+        Err(_) => return vec![],
+    };
+
+    let mut called_at = vec![];
+    let mut read = vec![];
+    for caller in tree {
+        if caller.incomplete {
+            return vec![];
+        }
+        let calls: Vec<_> = caller
+            .module_calls()
+            .into_iter()
+            .filter(|c| c.is_local())
+            .filter(|c| {
+                let source = caller.dir.join(c.source.as_deref().unwrap_or_default());
+                fs::canonicalize(source).is_ok_and(|s| s == dir)
+            })
+            .collect();
+        if calls.is_empty() {
+            continue;
+        }
+        called_at.extend(calls.iter().map(|c| c.call.location()));
+        for output_use in caller.entries(EntryType::ModuleOutputUse) {
+            let mut parts = output_use.name.splitn(2, '.');
+            let name = parts.next().unwrap_or_default();
+            if calls.iter().any(|c| c.call.name == name) {
+                // The whole module object may be passed on, every output
+                // counts as read then
+                read.push(parts.next().map(str::to_string));
+            }
+        }
+    }
+    if called_at.is_empty() || read.contains(&None) {
+        return vec![];
+    }
+
+    let note = format!("called at {}", called_at.join(", "));
+    module
+        .entries(EntryType::OutputDefinition)
+        .iter()
+        .filter(|output| !read.contains(&Some(output.name.clone())))
+        .map(|output| Finding::new(FindingKind::UnusedOutput, output).with_note(note.clone()))
+        .collect()
+}
+
+/// Extensions of templates, scripts, policies and other files that modules
+/// read with `file()` and friends.
+const ASSET_EXTENSIONS: &[&str] = &[
+    "tftpl", "tpl", "json", "sh", "ps1", "yaml", "yml", "rego", "sentinel", "cfg", "conf",
+];
+
+/// Files in the module directory that no file function reads, and paths
+/// that file functions read but don't exist.
+fn check_files(module: &Module) -> Vec<Finding> {
+    // `fileexists()` may well be given a missing path, and so may a file
+    // function it guards
+    let checks = module.entries(EntryType::FileExistenceCheck);
+    let references = module.entries(EntryType::FileReference);
+    let missing = references
+        .iter()
+        .filter(|r| !r.name.contains('*') && !module.dir.join(&r.name).exists())
+        .filter(|r| !checks.iter().any(|c| c.name == r.name))
+        .map(|r| Finding::new(FindingKind::FileNotFound, r));
+
+    let patterns: Vec<_> = references
+        .iter()
+        .chain(&checks)
+        .filter_map(|r| Pattern::new(&r.name).ok())
+        .collect();
+    let mut assets = vec![];
+    if !module.incomplete {
+        collect_assets(&module.dir, Path::new(""), &mut assets);
+    }
+    let unused = assets
+        .into_iter()
+        .filter(|asset| !patterns.iter().any(|p| p.matches_path(asset)))
+        .map(|asset| {
+            let file = Variable {
+                name: asset.to_string_lossy().to_string(),
+                at: module.dir.join(&asset).to_string_lossy().to_string(),
+                span: no_position(),
+            };
+            Finding::new(FindingKind::UnusedFile, &file)
+        });
+
+    missing.chain(unused).collect()
+}
+
+/// Asset files under `dir`, relative to the module directory. Hidden
+/// directories and directories of other modules are skipped.
+fn collect_assets(dir: &Path, relative: &Path, assets: &mut Vec<std::path::PathBuf>) {
+    let entries = match fs::read_dir(dir.join(relative)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut paths: Vec<_> = entries.filter_map(Result::ok).map(|e| e.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if !is_module_dir(&path) {
+                collect_assets(dir, &relative.join(name), assets);
+            }
+            continue;
+        }
+        let terraform = [".tf.json", ".tfvars.json"]
+            .iter()
+            .any(|e| name.ends_with(e));
+        let asset = ASSET_EXTENSIONS
+            .iter()
+            .any(|ext| name.ends_with(&format!(".{}", ext)));
+        if asset && !terraform {
+            assets.push(relative.join(name));
+        }
+    }
+}
+
+/// Checks literal values in var files against the `type` constraints of
+/// the variables they set.
+fn check_types(module: &Module) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut types = vec![];
+    for file in module.files.iter().filter(|f| f.file_type.is_source()) {
+        for block in file.body.blocks_of("variable") {
+            let (name, constraint) = match (block.labels.first(), block.body.attribute("type")) {
+                (Some(name), Some(constraint)) => (name, constraint),
+                _ => continue,
+            };
+            match Type::parse(&constraint.expr) {
+                Ok(ty) => types.push((name.clone(), ty)),
+                Err(e) => {
+                    let var = Variable {
+                        name: name.clone(),
+                        at: file.path.clone(),
+                        span: constraint.span,
+                    };
+                    findings
+                        .push(Finding::new(FindingKind::InvalidTypeConstraint, &var).with_note(e));
+                }
+            }
+        }
+    }
+
+    for file in module.files.iter().filter(|f| f.file_type.is_vars()) {
+        for value in file.body.attributes() {
+            let ty = match types.iter().find(|(name, _)| *name == value.name) {
+                Some((_, ty)) => ty,
+                None => continue,
+            };
+            let var = Variable {
+                name: value.name.clone(),
+                at: file.path.clone(),
+                span: value.span,
+            };
+            findings.extend(
+                ty.check(&value.expr)
+                    .into_iter()
+                    .map(|e| Finding::new(FindingKind::TypeMismatch, &var).with_note(e)),
+            );
+        }
+    }
+    findings
+}
+
+fn check_module_call(module: &Module, call: &ModuleCall) -> Vec<Finding> {
+    check_call(
+        module,
+        call,
+        format!(
+            "module {}, {}",
+            call.call.name,
+            call.source.as_deref().unwrap_or_default()
+        ),
+        FindingKind::UndeclaredModuleArgument,
+        Some(FindingKind::MissingModuleArgument),
+    )
+}
+
+/// Matches the arguments of a call to a local module against the variables
+/// the called module declares. Required variables are only checked with a
+/// `missing_kind`.
+fn check_call(
+    module: &Module,
+    call: &ModuleCall,
+    note: String,
+    undeclared_kind: FindingKind,
+    missing_kind: Option<FindingKind>,
+) -> Vec<Finding> {
+    let source = call.source.as_deref().unwrap_or_default();
+    let child_dir = module.dir.join(source);
+    if !child_dir.is_dir() {
+        return vec![Finding::new(FindingKind::ModuleSourceNotFound, &call.call)
+            .with_note(source.to_string())];
+    }
+
+    let child = match Module::load(&child_dir) {
+        Ok((child, _)) => child,
+        Err(e) => {
+            return vec![Finding::new(FindingKind::ModuleSourceNotFound, &call.call).with_note(e)]
+        }
+    };
+    let definitions = child.entries(EntryType::Definition);
+    let required = child.entries(EntryType::RequiredDefinition);
+
+    let undeclared_args = undeclared(&call.arguments, &definitions)
+        .into_iter()
+        .filter(|_| !child.incomplete)
+        .map(|arg| Finding::new(undeclared_kind, arg).with_note(note.clone()));
+
+    let missing_args = unset(&required, &call.arguments)
+        .into_iter()
+        .filter_map(|def| {
+            let at_call = Variable {
+                name: def.name.clone(),
+                ..call.call.clone()
+            };
+            Some(Finding::new(missing_kind?, &at_call).with_note(note.clone()))
+        });
+
+    undeclared_args.chain(missing_args).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module::module_dirs;
+    use std::path::Path;
+
+    fn findings(dir: &str) -> Vec<(FindingKind, String)> {
+        let (module, errors) = Module::load(Path::new(dir)).unwrap();
+        assert!(errors.is_empty());
+        analyse(&module)
+            .into_iter()
+            .map(|f| (f.kind, f.var.name))
+            .collect()
+    }
+
+    #[test]
+    fn test_fixtures() {
+        assert_eq!(
+            findings("tests/fixtures/has_unused"),
+            vec![
+                (
+                    FindingKind::UnusedDefinition,
+                    "legacy_switch_i_forgot_to_remove".to_string()
+                ),
+                (
+                    FindingKind::UnusedDefinition,
+                    "surprisingly_unimportant_variable".to_string()
+                ),
+                (FindingKind::UnusedValue, "some_random_variable".to_string()),
+                (FindingKind::RequiredNotSet, "instance_name".to_string()),
+            ]
+        );
+        assert!(findings("tests/fixtures/has_no_unused").is_empty());
+        // No var files, so nothing to say about required variables
+        assert!(findings("tests/fixtures/module_calls/modules/net").is_empty());
+    }
+
+    #[test]
+    fn test_incomplete_module() {
+        let dir = Path::new("tests/fixtures/has_parse_error");
+        let (module, errors) = Module::load(dir).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(module.incomplete);
+        // `used` is read in the file that failed to parse
+        assert!(analyse(&module).is_empty());
+    }
+
+    #[test]
+    fn test_extra_values() {
+        let (mut module, _) = Module::load(Path::new("tests/fixtures/has_unused")).unwrap();
+        module.extra_values =
+            crate::env::read_env_file(Path::new("tests/fixtures/var_files/ci.env")).unwrap();
+        let values: Vec<_> = analyse(&module)
+            .into_iter()
+            .filter(|f| f.kind == FindingKind::UnusedValue || f.kind == FindingKind::RequiredNotSet)
+            .map(|f| (f.kind, f.var.location()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (
+                    FindingKind::UnusedValue,
+                    "tests/fixtures/has_unused/some.tfvars:1:1".to_string()
+                ),
+                (
+                    FindingKind::UnusedValue,
+                    "tests/fixtures/var_files/ci.env:3:8".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_object_attributes() {
+        assert_eq!(
+            findings("tests/fixtures/has_unused_attributes"),
+            vec![
+                (FindingKind::UnusedObjectAttribute, "settings.b".to_string()),
+                (
+                    FindingKind::UnusedObjectAttribute,
+                    "settings.db.port".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_files() {
+        let (module, _) = Module::load(Path::new("tests/fixtures/has_unused_files")).unwrap();
+        let files: Vec<_> = analyse(&module)
+            .into_iter()
+            .map(|f| (f.kind, f.var.location()))
+            .collect();
+        let dir = "tests/fixtures/has_unused_files";
+        assert_eq!(
+            files,
+            vec![
+                (FindingKind::FileNotFound, format!("{}/main.tf:8:26", dir)),
+                (
+                    FindingKind::UnusedFile,
+                    format!("{}/policies/legacy.json", dir)
+                ),
+                (
+                    FindingKind::UnusedFile,
+                    format!("{}/scripts/old-bootstrap.sh", dir)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shadowed_by_var_file() {
+        let dir = "tests/fixtures/has_duplicates";
+        let (mut module, _) = Module::load(Path::new(dir)).unwrap();
+        module.set_var_files(&[Path::new(dir).join("terraform.tfvars")], true);
+        let shadowed: Vec<_> = analyse(&module)
+            .into_iter()
+            .filter(|f| f.kind == FindingKind::ShadowedValue)
+            .map(|f| (f.var.location(), f.note.unwrap_or_default()))
+            .collect();
+        let wins = format!("{}/terraform.tfvars:2:1 sets \"eu-west-1a\"", dir);
+        assert_eq!(
+            shadowed,
+            vec![
+                (format!("{}/a.auto.tfvars:1:1", dir), wins.clone()),
+                (format!("{}/zones.auto.tfvars:1:1", dir), wins),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_locals() {
+        assert_eq!(
+            findings("tests/fixtures/has_unused_locals"),
+            vec![(FindingKind::UnusedLocal, "forgotten".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_unused_data_sources() {
+        assert_eq!(
+            findings("tests/fixtures/has_unused_data"),
+            vec![(
+                FindingKind::UnusedDataSource,
+                "aws_iam_policy_document.stale".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unused_provider_aliases() {
+        assert_eq!(
+            findings("tests/fixtures/has_unused_provider_alias"),
+            vec![(
+                FindingKind::UnusedProviderAlias,
+                "aws.eu_central_1".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unused_required_providers() {
+        assert_eq!(
+            findings("tests/fixtures/has_unused_provider"),
+            vec![(FindingKind::UnusedRequiredProvider, "tls".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_undeclared_variables() {
+        let (module, _) = Module::load(Path::new("tests/fixtures/has_undeclared")).unwrap();
+        let undeclared: Vec<_> = analyse(&module)
+            .into_iter()
+            .filter(|f| f.kind == FindingKind::UndeclaredVariable)
+            .map(|f| f.var.location())
+            .collect();
+        assert_eq!(
+            undeclared,
+            vec![
+                "tests/fixtures/has_undeclared/main.tf:7:19",
+                "tests/fixtures/has_undeclared/main.tf:9:18",
+            ]
+        );
+        assert_eq!(FindingKind::UndeclaredVariable.severity(), Severity::Error);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let (module, _) = Module::load(Path::new("tests/fixtures/has_duplicates")).unwrap();
+        let duplicates: Vec<_> = analyse(&module)
+            .into_iter()
+            .map(|f| (f.kind, f.var.location(), f.note.unwrap_or_default()))
+            .collect();
+        let dir = "tests/fixtures/has_duplicates";
+        assert_eq!(
+            duplicates,
+            vec![
+                (
+                    FindingKind::DuplicateDefinition,
+                    format!("{}/main.tf:1:1", dir),
+                    format!("also at {}/vars.tf:1:1", dir)
+                ),
+                (
+                    FindingKind::DuplicateDefinition,
+                    format!("{}/vars.tf:1:1", dir),
+                    format!("also at {}/main.tf:1:1", dir)
+                ),
+                (
+                    FindingKind::DuplicateValue,
+                    format!("{}/terraform.tfvars:1:1", dir),
+                    format!("also at {}/terraform.tfvars:3:1", dir)
+                ),
+                (
+                    FindingKind::DuplicateValue,
+                    format!("{}/terraform.tfvars:3:1", dir),
+                    format!("also at {}/terraform.tfvars:1:1", dir)
+                ),
+                (
+                    FindingKind::ShadowedValue,
+                    format!("{}/terraform.tfvars:2:1", dir),
+                    format!("{}/zones.auto.tfvars:1:1 sets \"eu-west-1b\"", dir)
+                ),
+                (
+                    FindingKind::ShadowedValue,
+                    format!("{}/a.auto.tfvars:1:1", dir),
+                    format!("{}/zones.auto.tfvars:1:1 sets \"eu-west-1b\"", dir)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_mismatches() {
+        let (module, _) = Module::load(Path::new("tests/fixtures/has_type_mismatch")).unwrap();
+        let mismatches: Vec<_> = analyse(&module)
+            .into_iter()
+            .filter(|f| f.kind.severity() == Severity::Error)
+            .map(|f| (f.kind, f.var.location(), f.note.unwrap_or_default()))
+            .collect();
+        let dir = "tests/fixtures/has_type_mismatch";
+        assert_eq!(
+            mismatches,
+            vec![
+                (
+                    FindingKind::InvalidTypeConstraint,
+                    format!("{}/vars.tf:20:3", dir),
+                    "unknown type strnig".to_string()
+                ),
+                (
+                    FindingKind::TypeMismatch,
+                    format!("{}/terraform.tfvars:1:1", dir),
+                    "expected number, got string".to_string()
+                ),
+                (
+                    FindingKind::TypeMismatch,
+                    format!("{}/terraform.tfvars:3:1", dir),
+                    ".name: required attribute is missing".to_string()
+                ),
+                (
+                    FindingKind::TypeMismatch,
+                    format!("{}/terraform.tfvars:3:1", dir),
+                    ".tags[\"team\"]: expected string, got tuple".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_environments() {
+        let dir = "tests/fixtures/environments";
+        let (module, _) = Module::load(Path::new(dir)).unwrap();
+        let environments: Vec<_> = ["prod", "staging"]
+            .iter()
+            .map(|name| {
+                let path = format!("{}/envs/{}.tfvars", dir, name);
+                Environment::load(name.to_string(), &[path.into()]).0
+            })
+            .collect();
+        let findings: Vec<_> = analyse_environments(&module, &environments)
+            .into_iter()
+            .map(|f| (f.kind, f.var.name, f.note.unwrap_or_default()))
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    FindingKind::UnusedValue,
+                    "legacy_ami".to_string(),
+                    "in every environment".to_string()
+                ),
+                (
+                    FindingKind::UnusedValueInSomeEnvironments,
+                    "debug".to_string(),
+                    "set in staging".to_string()
+                ),
+                (
+                    FindingKind::RequiredNotSet,
+                    "instance_count".to_string(),
+                    "in staging".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_outputs() {
+        let tree: Vec<_> = module_dirs(Path::new("tests/fixtures/module_calls"))
+            .unwrap()
+            .iter()
+            .map(|dir| Module::load(dir).unwrap().0)
+            .collect();
+        let unused: Vec<_> = tree
+            .iter()
+            .flat_map(|module| unused_outputs(module, &tree))
+            .map(|f| (f.kind, f.var.name))
+            .collect();
+        assert_eq!(
+            unused,
+            vec![(FindingKind::UnusedOutput, "vpc_arn".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_terragrunt_inputs() {
+        let tree: Vec<_> = module_dirs(Path::new("tests/fixtures/terragrunt"))
+            .unwrap()
+            .iter()
+            .map(|dir| Module::load(dir).unwrap().0)
+            .collect();
+        let inputs: Vec<_> = tree
+            .iter()
+            .flat_map(analyse)
+            .map(|f| (f.kind, f.var.location()))
+            .collect();
+        assert_eq!(
+            inputs,
+            vec![
+                (
+                    FindingKind::UndeclaredInput,
+                    "tests/fixtures/terragrunt/live/prod/vpc/terragrunt.hcl:6:3".to_string()
+                ),
+                (
+                    FindingKind::MissingInput,
+                    "tests/fixtures/terragrunt/live/prod/vpc/terragrunt.hcl:1:1".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_module_calls() {
+        assert_eq!(
+            findings("tests/fixtures/module_calls"),
+            vec![
+                (FindingKind::UndeclaredModuleArgument, "colour".to_string()),
+                (FindingKind::MissingModuleArgument, "name".to_string()),
+                (FindingKind::ModuleSourceNotFound, "gone".to_string()),
+            ]
+        );
+    }
+}
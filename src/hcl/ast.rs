@@ -0,0 +1,351 @@
+use super::Span;
+
+/// A parsed file: its root body and the comments found along the way.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    pub body: Body,
+    pub comments: Vec<Comment>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Comment {
+    /// Comment text without the `#`, `//` or `/* */` markers
+    pub text: String,
+    /// Span of `text`, markers excluded
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Body {
+    pub structures: Vec<Structure>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Structure {
+    Attribute(Attribute),
+    Block(Block),
+}
+
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub expr: Expression,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub ident: String,
+    pub labels: Vec<String>,
+    pub body: Body,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expression {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Null,
+    // Literal values and the parts below that only matter when evaluating
+    // are kept for a faithful tree, though no check reads them yet
+    Bool(#[allow(dead_code)] bool),
+    Number(#[allow(dead_code)] String),
+    /// Quoted string or heredoc
+    Template(Vec<TemplatePart>),
+    Tuple(Vec<Expression>),
+    Object(Vec<ObjectItem>),
+    Variable(String),
+    FunctionCall {
+        name: String,
+        args: Vec<Expression>,
+        #[allow(dead_code)]
+        expand_final: bool,
+    },
+    GetAttr(Box<Expression>, String),
+    Index(Box<Expression>, Box<Expression>),
+    Splat(Box<Expression>),
+    Unary(Operator, Box<Expression>),
+    Binary(
+        #[allow(dead_code)] Operator,
+        Box<Expression>,
+        Box<Expression>,
+    ),
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+    For(Box<ForExpr>),
+    Parens(Box<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Not,
+    Negate,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectItem {
+    pub key: Expression,
+    pub value: Expression,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForExpr {
+    #[allow(dead_code)]
+    pub key_var: Option<String>,
+    #[allow(dead_code)]
+    pub value_var: String,
+    pub collection: Expression,
+    pub key: Option<Expression>,
+    pub value: Expression,
+    pub condition: Option<Expression>,
+    #[allow(dead_code)]
+    pub grouping: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum TemplatePart {
+    Literal(String),
+    Interpolation(Expression),
+    Directive(Directive),
+}
+
+#[derive(Debug, Clone)]
+pub enum Directive {
+    If(Expression),
+    Else,
+    EndIf,
+    For {
+        #[allow(dead_code)]
+        key_var: Option<String>,
+        #[allow(dead_code)]
+        value_var: String,
+        collection: Expression,
+    },
+    EndFor,
+}
+
+/// A chain of attribute accesses rooted at a variable, such as
+/// `var.instance_name` or `data.aws_ami.ubuntu.id`. `attrs` stops at the
+/// first step that is not a static attribute name or a literal index key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Traversal {
+    pub root: String,
+    pub attrs: Vec<String>,
+    /// For each of `attrs`, whether it is an index key like `["a"]`
+    pub indexed: Vec<bool>,
+    pub span: Span,
+}
+
+impl Body {
+    pub fn attributes(&self) -> impl Iterator<Item = &Attribute> {
+        self.structures.iter().filter_map(|s| match s {
+            Structure::Attribute(attr) => Some(attr),
+            Structure::Block(_) => None,
+        })
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &Block> {
+        self.structures.iter().filter_map(|s| match s {
+            Structure::Block(block) => Some(block),
+            Structure::Attribute(_) => None,
+        })
+    }
+
+    pub fn blocks_of<'a>(&'a self, ident: &'a str) -> impl Iterator<Item = &'a Block> {
+        self.blocks().filter(move |b| b.ident == ident)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes().find(|a| a.name == name)
+    }
+
+    /// Expressions of every attribute in this body and in all nested blocks.
+    pub fn expressions(&self) -> Vec<&Expression> {
+        let mut exprs = vec![];
+        for s in &self.structures {
+            match s {
+                Structure::Attribute(attr) => exprs.push(&attr.expr),
+                Structure::Block(block) => exprs.extend(block.body.expressions()),
+            }
+        }
+        exprs
+    }
+
+    pub fn traversals(&self) -> Vec<Traversal> {
+        self.expressions()
+            .into_iter()
+            .flat_map(Expression::traversals)
+            .collect()
+    }
+}
+
+impl Expression {
+    /// Calls `f` for this expression and every expression nested in it.
+    pub fn walk<F: FnMut(&Expression)>(&self, f: &mut F) {
+        f(self);
+        for child in self.children() {
+            child.walk(f);
+        }
+    }
+
+    /// Expressions directly nested in this one.
+    pub fn children(&self) -> Vec<&Expression> {
+        match &self.kind {
+            ExprKind::Null | ExprKind::Bool(_) | ExprKind::Number(_) | ExprKind::Variable(_) => {
+                vec![]
+            }
+            ExprKind::Template(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    TemplatePart::Interpolation(e) => Some(e),
+                    TemplatePart::Directive(Directive::If(e)) => Some(e),
+                    TemplatePart::Directive(Directive::For { collection, .. }) => Some(collection),
+                    _ => None,
+                })
+                .collect(),
+            ExprKind::Tuple(items) => items.iter().collect(),
+            // A bare identifier as an object key is a literal name
+            ExprKind::Object(items) => items
+                .iter()
+                .flat_map(|item| match item.key.kind {
+                    ExprKind::Variable(_) => vec![&item.value],
+                    _ => vec![&item.key, &item.value],
+                })
+                .collect(),
+            ExprKind::FunctionCall { args, .. } => args.iter().collect(),
+            ExprKind::GetAttr(e, _)
+            | ExprKind::Splat(e)
+            | ExprKind::Unary(_, e)
+            | ExprKind::Parens(e) => vec![e],
+            ExprKind::Index(e, key) => vec![e, key],
+            ExprKind::Binary(_, lhs, rhs) => vec![lhs, rhs],
+            ExprKind::Conditional(cond, t, e) => vec![cond, t, e],
+            ExprKind::For(for_expr) => {
+                let mut children = vec![&for_expr.collection];
+                children.extend(&for_expr.key);
+                children.push(&for_expr.value);
+                children.extend(&for_expr.condition);
+                children
+            }
+        }
+    }
+
+    /// The string value of a template without interpolations or directives.
+    pub fn as_literal_string(&self) -> Option<String> {
+        match &self.kind {
+            ExprKind::Template(parts) => {
+                let mut s = String::new();
+                for part in parts {
+                    match part {
+                        TemplatePart::Literal(lit) => s.push_str(lit),
+                        _ => return None,
+                    }
+                }
+                Some(s)
+            }
+            _ => None,
+        }
+    }
+
+    /// Name of an object key: either a bare identifier or a literal string.
+    pub fn as_key(&self) -> Option<String> {
+        match &self.kind {
+            ExprKind::Variable(name) => Some(name.clone()),
+            ExprKind::Parens(e) => e.as_literal_string(),
+            _ => self.as_literal_string(),
+        }
+    }
+
+    /// This expression as a traversal, if it is one.
+    pub fn as_traversal(&self) -> Option<Traversal> {
+        let mut steps = vec![];
+        let mut current = self;
+        loop {
+            match &current.kind {
+                ExprKind::GetAttr(inner, name) => {
+                    steps.push((Some(name.clone()), false));
+                    current = inner;
+                }
+                ExprKind::Index(inner, key) => {
+                    steps.push((key.as_literal_string(), true));
+                    current = inner;
+                }
+                ExprKind::Splat(inner) => {
+                    steps.push((None, false));
+                    current = inner;
+                }
+                ExprKind::Variable(root) => {
+                    let (attrs, indexed) = steps
+                        .into_iter()
+                        .rev()
+                        .map_while(|(name, indexed)| Some((name?, indexed)))
+                        .unzip();
+                    return Some(Traversal {
+                        root: root.clone(),
+                        attrs,
+                        indexed,
+                        span: self.span,
+                    });
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// All traversals in this expression, each one as long as possible.
+    pub fn traversals(&self) -> Vec<Traversal> {
+        let mut found = vec![];
+        self.collect_traversals(&mut found);
+        found
+    }
+
+    fn collect_traversals(&self, found: &mut Vec<Traversal>) {
+        match &self.kind {
+            ExprKind::GetAttr(..)
+            | ExprKind::Index(..)
+            | ExprKind::Splat(..)
+            | ExprKind::Variable(_) => {
+                if let Some(traversal) = self.as_traversal() {
+                    found.push(traversal);
+                }
+                // Index keys and non-variable roots may hold traversals too
+                let mut current = self;
+                loop {
+                    match &current.kind {
+                        ExprKind::GetAttr(inner, _) | ExprKind::Splat(inner) => current = inner,
+                        ExprKind::Index(inner, key) => {
+                            key.collect_traversals(found);
+                            current = inner;
+                        }
+                        ExprKind::Variable(_) => break,
+                        _ => {
+                            current.collect_traversals(found);
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => {
+                for child in self.children() {
+                    child.collect_traversals(found);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! The JSON syntax. The document is parsed as an expression and its root
+//! object is then mapped onto a body, the way HCL does it: which root keys
+//! are blocks, and how many labels they take, is up to the caller.
+
+use super::ast::*;
+use super::parser::parse_json_value;
+use super::{Error, Pos, Span};
+
+/// Parses a file in the JSON syntax. `block_labels` returns the number of
+/// labels for root keys that are block types, and `None` for attributes.
+pub fn parse_json(
+    src: &str,
+    block_labels: &dyn Fn(&str) -> Option<usize>,
+) -> Result<Document, Error> {
+    let root = parse_json_value(src)?;
+    let items = match &root.kind {
+        ExprKind::Object(items) => items,
+        _ => return Err(Error::new("expected a JSON object", root.span.start)),
+    };
+
+    let mut body = Body::default();
+    for item in items {
+        let name = key_name(&item.key)?;
+        match block_labels(&name) {
+            Some(labels) => blocks(
+                &name,
+                &item.value,
+                labels,
+                &mut vec![],
+                item.key.span.start,
+                &mut body.structures,
+            )?,
+            None => body.structures.push(Structure::Attribute(Attribute {
+                name,
+                span: item_span(item),
+                expr: item.value.clone(),
+            })),
+        }
+    }
+
+    Ok(Document {
+        body,
+        comments: vec![],
+    })
+}
+
+/// From the key to the end of the value, like the span of an attribute in
+/// the native syntax.
+fn item_span(item: &ObjectItem) -> Span {
+    Span {
+        start: item.key.span.start,
+        end: item.value.span.end,
+    }
+}
+
+fn key_name(key: &Expression) -> Result<String, Error> {
+    key.as_literal_string()
+        .ok_or_else(|| Error::new("object keys must be literal strings", key.span.start))
+}
+
+/// Unwraps `labels_left` levels of nested objects into block labels. Any
+/// level may also be an array of objects. A block starts at the key of its
+/// last label, `start`.
+fn blocks(
+    ident: &str,
+    value: &Expression,
+    labels_left: usize,
+    labels: &mut Vec<String>,
+    start: Pos,
+    out: &mut Vec<Structure>,
+) -> Result<(), Error> {
+    let items = match &value.kind {
+        ExprKind::Tuple(elements) => {
+            for element in elements {
+                blocks(ident, element, labels_left, labels, start, out)?;
+            }
+            return Ok(());
+        }
+        ExprKind::Object(items) => items,
+        _ => return Err(Error::new("expected a JSON object", value.span.start)),
+    };
+
+    if labels_left == 0 {
+        let mut body = Body::default();
+        for item in items {
+            body.structures.push(Structure::Attribute(Attribute {
+                name: key_name(&item.key)?,
+                span: item_span(item),
+                expr: item.value.clone(),
+            }));
+        }
+        out.push(Structure::Block(Block {
+            ident: ident.to_string(),
+            labels: labels.clone(),
+            body,
+            span: Span {
+                start,
+                end: value.span.end,
+            },
+        }));
+        return Ok(());
+    }
+
+    for item in items {
+        labels.push(key_name(&item.key)?);
+        blocks(
+            ident,
+            &item.value,
+            labels_left - 1,
+            labels,
+            item.key.span.start,
+            out,
+        )?;
+        labels.pop();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(ident: &str) -> Option<usize> {
+        match ident {
+            "variable" => Some(1),
+            "resource" => Some(2),
+            "locals" => Some(0),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_blocks_from_json() {
+        let src = r#"{
+          "variable": {
+            "region": { "default": "eu-west-1" },
+            "zones": { "type": "list(string)" }
+          },
+          "locals": [{ "a": 1 }, { "b": -2.5e1 }],
+          "resource": {
+            "aws_instance": {
+              "web": { "ami": "${var.ami}", "tags": { "Name": "x" } }
+            }
+          },
+          "extra": null
+        }"#;
+        let body = parse_json(src, &labels).unwrap().body;
+
+        let variables: Vec<_> = body
+            .blocks_of("variable")
+            .map(|b| b.labels.join("."))
+            .collect();
+        assert_eq!(variables, vec!["region", "zones"]);
+        assert_eq!(body.blocks_of("locals").count(), 2);
+
+        let resource = body.blocks_of("resource").next().unwrap();
+        assert_eq!(resource.labels, vec!["aws_instance", "web"]);
+        let traversal = &resource.body.traversals()[0];
+        assert_eq!(
+            (traversal.root.as_str(), traversal.attrs.clone()),
+            ("var", vec!["ami".to_string()])
+        );
+
+        assert!(body.attribute("extra").is_some());
+    }
+
+    #[test]
+    fn test_spans_start_at_keys() {
+        let start = |span: Span| (span.start.line, span.start.column);
+        let body = parse_json(r#"{ "region": "eu", "extra": 1}"#, &|_| None)
+            .unwrap()
+            .body;
+        assert_eq!(start(body.attribute("extra").unwrap().span), (1, 19));
+
+        let src = "{\n  \"variable\": {\n    \"region\": {}\n  }\n}";
+        let body = parse_json(src, &labels).unwrap().body;
+        let variable = body.blocks_of("variable").next().unwrap();
+        assert_eq!(start(variable.span), (3, 5));
+    }
+
+    #[test]
+    fn test_json_escapes() {
+        let doc = parse_json(r#"{ "extra": "a\fb\bc\u0041" }"#, &labels).unwrap();
+        let value = doc
+            .body
+            .attribute("extra")
+            .unwrap()
+            .expr
+            .as_literal_string();
+        assert_eq!(value.as_deref(), Some("a\u{c}b\u{8}cA"));
+        assert!(crate::hcl::parse(r#"a = "\f""#).is_err());
+    }
+
+    #[test]
+    fn test_json_root_must_be_object() {
+        let err = parse_json("[1, 2]", &labels).unwrap_err();
+        assert_eq!(err.message, "expected a JSON object");
+    }
+}
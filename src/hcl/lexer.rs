@@ -0,0 +1,592 @@
+use super::{Comment, Error, Pos, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Ident(String),
+    Number(String),
+    /// Literal part of a quoted template or heredoc, escapes already resolved
+    TemplateLit(String),
+    OQuote,
+    CQuote,
+    OHeredoc,
+    CHeredoc,
+    /// `${`
+    TemplateInterp,
+    /// `%{`
+    TemplateControl,
+    /// `}` closing an interpolation or a directive
+    TemplateSeqEnd,
+    LBrace,
+    RBrace,
+    LBrack,
+    RBrack,
+    LParen,
+    RParen,
+    Comma,
+    Dot,
+    Ellipsis,
+    Colon,
+    Question,
+    Equal,
+    FatArrow,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    And,
+    Or,
+    Bang,
+    EqualOp,
+    NotEqual,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Newline,
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+enum Mode {
+    Normal,
+    Brace,
+    Interp,
+    Quoted,
+    Heredoc(String),
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: Pos,
+    modes: Vec<Mode>,
+    tokens: Vec<Token>,
+    comments: Vec<Comment>,
+    /// JSON strings take JSON's escapes, which include `\b` and `\f`
+    json: bool,
+}
+
+/// Splits `src` into tokens. Comments don't become tokens, they are
+/// returned separately.
+pub fn tokenize(src: &str, json: bool) -> Result<(Vec<Token>, Vec<Comment>), Error> {
+    let mut lexer = Lexer {
+        src,
+        json,
+        pos: Pos::start(),
+        modes: vec![Mode::Normal],
+        tokens: vec![],
+        comments: vec![],
+    };
+    // Editors on Windows like to start files with a byte order mark
+    if src.starts_with('\u{feff}') {
+        lexer.pos.byte += '\u{feff}'.len_utf8();
+    }
+
+    loop {
+        match lexer.modes.last() {
+            Some(Mode::Quoted) => lexer.lex_quoted()?,
+            Some(Mode::Heredoc(marker)) => {
+                let marker = marker.clone();
+                lexer.lex_heredoc(&marker)?
+            }
+            _ => {
+                if !lexer.lex_normal()? {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok((lexer.tokens, lexer.comments))
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+impl<'a> Lexer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos.byte..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos.byte += c.len_utf8();
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(c)
+    }
+
+    fn bump_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.bump();
+        }
+    }
+
+    fn push(&mut self, kind: TokenKind, start: Pos) {
+        self.tokens.push(Token {
+            kind,
+            span: Span {
+                start,
+                end: self.pos,
+            },
+        });
+    }
+
+    fn push_literal(&mut self, lit: &mut String, start: Pos) {
+        if !lit.is_empty() {
+            let lit = std::mem::take(lit);
+            self.push(TokenKind::TemplateLit(lit), start);
+        }
+    }
+
+    /// Lexes a single token (or skips whitespace and a comment) in the
+    /// expression-level modes. Returns `false` once the end of input is
+    /// reached.
+    fn lex_normal(&mut self) -> Result<bool, Error> {
+        let start = self.pos;
+        let c = match self.peek() {
+            Some(c) => c,
+            None => {
+                self.push(TokenKind::Eof, start);
+                return Ok(false);
+            }
+        };
+
+        match c {
+            ' ' | '\t' | '\r' => {
+                self.bump();
+            }
+            '\n' => {
+                self.bump();
+                self.push(TokenKind::Newline, start);
+            }
+            '#' => self.lex_line_comment(1),
+            '/' if self.peek_nth(1) == Some('/') => self.lex_line_comment(2),
+            '/' if self.peek_nth(1) == Some('*') => self.lex_block_comment()?,
+            '"' => {
+                self.bump();
+                self.push(TokenKind::OQuote, start);
+                self.modes.push(Mode::Quoted);
+            }
+            '<' if self.heredoc_header().is_some() => {
+                let (marker, len) = self.heredoc_header().unwrap();
+                self.bump_n(len);
+                self.push(TokenKind::OHeredoc, start);
+                self.modes.push(Mode::Heredoc(marker));
+            }
+            '{' => {
+                self.bump();
+                self.push(TokenKind::LBrace, start);
+                self.modes.push(Mode::Brace);
+            }
+            '}' => {
+                self.bump();
+                self.close_brace(start);
+            }
+            '~' if self.peek_nth(1) == Some('}') && self.modes.last() == Some(&Mode::Interp) => {
+                self.bump_n(2);
+                self.close_brace(start);
+            }
+            c if is_ident_start(c) => {
+                let mut ident = String::new();
+                while let Some(c) = self.peek().filter(|c| is_ident_char(*c)) {
+                    ident.push(c);
+                    self.bump();
+                    // Provider functions are namespaced: provider::aws::arn_parse
+                    if self.rest().starts_with("::") && self.peek_nth(2).is_some_and(is_ident_start)
+                    {
+                        ident.push_str("::");
+                        self.bump_n(2);
+                    }
+                }
+                self.push(TokenKind::Ident(ident), start);
+            }
+            c if c.is_ascii_digit() => self.lex_number(),
+            _ => self.lex_operator()?,
+        }
+
+        Ok(true)
+    }
+
+    fn close_brace(&mut self, start: Pos) {
+        match self.modes.last() {
+            Some(Mode::Interp) => {
+                self.modes.pop();
+                self.push(TokenKind::TemplateSeqEnd, start);
+            }
+            Some(Mode::Brace) => {
+                self.modes.pop();
+                self.push(TokenKind::RBrace, start);
+            }
+            _ => self.push(TokenKind::RBrace, start),
+        }
+    }
+
+    fn lex_line_comment(&mut self, marker_len: usize) {
+        self.bump_n(marker_len);
+        let start = self.pos;
+        let mut text = String::new();
+        while let Some(c) = self.peek().filter(|c| *c != '\n') {
+            text.push(c);
+            self.bump();
+        }
+        self.push_comment(text, start);
+    }
+
+    fn lex_block_comment(&mut self) -> Result<(), Error> {
+        let opening = self.pos;
+        self.bump_n(2);
+        let start = self.pos;
+        let mut text = String::new();
+        loop {
+            if self.rest().starts_with("*/") {
+                self.push_comment(text, start);
+                self.bump_n(2);
+                return Ok(());
+            }
+            match self.bump() {
+                Some(c) => text.push(c),
+                None => return Err(Error::new("unterminated comment", opening)),
+            }
+        }
+    }
+
+    fn push_comment(&mut self, text: String, start: Pos) {
+        self.comments.push(Comment {
+            text,
+            span: Span {
+                start,
+                end: self.pos,
+            },
+        });
+    }
+
+    /// Recognizes `<<MARKER` or `<<-MARKER` followed by a newline and
+    /// returns the marker and the length of the header in characters.
+    fn heredoc_header(&self) -> Option<(String, usize)> {
+        let rest = self.rest().strip_prefix("<<")?;
+        let (rest, mut len) = match rest.strip_prefix('-') {
+            Some(rest) => (rest, 3),
+            None => (rest, 2),
+        };
+        if !rest.starts_with(is_ident_start) {
+            return None;
+        }
+        let marker: String = rest.chars().take_while(|c| is_ident_char(*c)).collect();
+        len += marker.chars().count();
+        let rest = &rest[marker.len()..];
+        if rest.starts_with('\n') {
+            Some((marker, len + 1))
+        } else if rest.starts_with("\r\n") {
+            Some((marker, len + 2))
+        } else {
+            None
+        }
+    }
+
+    fn lex_number(&mut self) {
+        let start = self.pos;
+        let mut number = String::new();
+        let take_digits = |lexer: &mut Lexer, number: &mut String| {
+            while let Some(c) = lexer.peek().filter(char::is_ascii_digit) {
+                number.push(c);
+                lexer.bump();
+            }
+        };
+        take_digits(self, &mut number);
+        if self.peek() == Some('.') && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) {
+            number.push('.');
+            self.bump();
+            take_digits(self, &mut number);
+        }
+        if let Some(e) = self.peek().filter(|c| *c == 'e' || *c == 'E') {
+            let sign = self.peek_nth(1).filter(|c| *c == '+' || *c == '-');
+            let digit_at = if sign.is_some() { 2 } else { 1 };
+            if self.peek_nth(digit_at).is_some_and(|c| c.is_ascii_digit()) {
+                number.push(e);
+                self.bump();
+                if let Some(sign) = sign {
+                    number.push(sign);
+                    self.bump();
+                }
+                take_digits(self, &mut number);
+            }
+        }
+        self.push(TokenKind::Number(number), start);
+    }
+
+    fn lex_operator(&mut self) -> Result<(), Error> {
+        use TokenKind::*;
+
+        let start = self.pos;
+        let c = self.peek().unwrap_or_default();
+        let next = self.peek_nth(1);
+        let (kind, len) = match (c, next) {
+            ('=', Some('=')) => (EqualOp, 2),
+            ('=', Some('>')) => (FatArrow, 2),
+            ('=', _) => (Equal, 1),
+            ('!', Some('=')) => (NotEqual, 2),
+            ('!', _) => (Bang, 1),
+            ('<', Some('=')) => (Le, 2),
+            ('<', _) => (Lt, 1),
+            ('>', Some('=')) => (Ge, 2),
+            ('>', _) => (Gt, 1),
+            ('&', Some('&')) => (And, 2),
+            ('|', Some('|')) => (Or, 2),
+            ('.', _) if self.rest().starts_with("...") => (Ellipsis, 3),
+            ('.', _) => (Dot, 1),
+            ('+', _) => (Plus, 1),
+            ('-', _) => (Minus, 1),
+            ('*', _) => (Star, 1),
+            ('/', _) => (Slash, 1),
+            ('%', _) => (Percent, 1),
+            ('?', _) => (Question, 1),
+            (':', _) => (Colon, 1),
+            (',', _) => (Comma, 1),
+            ('(', _) => (LParen, 1),
+            (')', _) => (RParen, 1),
+            ('[', _) => (LBrack, 1),
+            (']', _) => (RBrack, 1),
+            _ => return Err(Error::new(format!("unexpected character {:?}", c), start)),
+        };
+        self.bump_n(len);
+        self.push(kind, start);
+        Ok(())
+    }
+
+    /// Opens an interpolation or a directive if one starts here, flushing
+    /// the literal collected so far.
+    fn template_sequence(&mut self, lit: &mut String, lit_start: Pos) -> bool {
+        let kind = if self.rest().starts_with("${") {
+            TokenKind::TemplateInterp
+        } else if self.rest().starts_with("%{") {
+            TokenKind::TemplateControl
+        } else {
+            return false;
+        };
+        self.push_literal(lit, lit_start);
+        let start = self.pos;
+        self.bump_n(2);
+        if self.peek() == Some('~') {
+            self.bump();
+        }
+        self.push(kind, start);
+        self.modes.push(Mode::Interp);
+        true
+    }
+
+    /// Handles `$${` and `%%{`, which stand for literal `${` and `%{`.
+    fn template_escape(&mut self, lit: &mut String) -> bool {
+        if self.rest().starts_with("$${") || self.rest().starts_with("%%{") {
+            self.bump();
+            lit.push(self.bump().unwrap_or_default());
+            lit.push(self.bump().unwrap_or_default());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn lex_quoted(&mut self) -> Result<(), Error> {
+        let start = self.pos;
+        let mut lit = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => {
+                    return Err(Error::new("unterminated template string", self.pos))
+                }
+                Some('"') => {
+                    self.push_literal(&mut lit, start);
+                    let quote = self.pos;
+                    self.bump();
+                    self.push(TokenKind::CQuote, quote);
+                    self.modes.pop();
+                    return Ok(());
+                }
+                Some('\\') => lit.push(self.lex_escape()?),
+                Some('$') | Some('%') => {
+                    if self.template_sequence(&mut lit, start) {
+                        return Ok(());
+                    }
+                    if !self.template_escape(&mut lit) {
+                        lit.push(self.bump().unwrap_or_default());
+                    }
+                }
+                Some(c) => {
+                    lit.push(c);
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    fn lex_escape(&mut self) -> Result<char, Error> {
+        let start = self.pos;
+        self.bump();
+        let c = match self.bump() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') if self.json => '\u{8}',
+            Some('f') if self.json => '\u{c}',
+            Some(u) if u == 'u' || u == 'U' => {
+                let len = if u == 'u' { 4 } else { 8 };
+                let hex: String = self.rest().chars().take(len).collect();
+                let code = u32::from_str_radix(&hex, 16)
+                    .map_err(|_| Error::new("invalid unicode escape", start))?;
+                self.bump_n(len);
+                std::char::from_u32(code).unwrap_or(std::char::REPLACEMENT_CHARACTER)
+            }
+            _ => return Err(Error::new("invalid escape sequence", start)),
+        };
+        Ok(c)
+    }
+
+    fn lex_heredoc(&mut self, marker: &str) -> Result<(), Error> {
+        let at_line_start = self.src[..self.pos.byte].ends_with('\n');
+        if at_line_start {
+            let line = self.rest().split('\n').next().unwrap_or("");
+            if line.trim() == marker {
+                let indent = line.len() - line.trim_start().len();
+                self.bump_n(indent);
+                let start = self.pos;
+                self.bump_n(marker.chars().count());
+                self.push(TokenKind::CHeredoc, start);
+                self.modes.pop();
+                return Ok(());
+            }
+        }
+
+        let start = self.pos;
+        let mut lit = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(Error::new("unterminated heredoc", start)),
+                Some('\n') => {
+                    lit.push('\n');
+                    self.bump();
+                    self.push_literal(&mut lit, start);
+                    return Ok(());
+                }
+                Some('$') | Some('%') => {
+                    if self.template_sequence(&mut lit, start) {
+                        return Ok(());
+                    }
+                    if !self.template_escape(&mut lit) {
+                        lit.push(self.bump().unwrap_or_default());
+                    }
+                }
+                Some(c) => {
+                    lit.push(c);
+                    self.bump();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenKind::*;
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        tokenize(src, false)
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|t| t.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_template_tokens() {
+        assert_eq!(
+            kinds(r#"a = "x${var.y}$${z}""#),
+            vec![
+                Ident("a".to_string()),
+                Equal,
+                OQuote,
+                TemplateLit("x".to_string()),
+                TemplateInterp,
+                Ident("var".to_string()),
+                Dot,
+                Ident("y".to_string()),
+                TemplateSeqEnd,
+                TemplateLit("${z}".to_string()),
+                CQuote,
+                Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments_are_skipped() {
+        let src = "# var.a\n// var.b\n/* var.c */ a";
+        assert_eq!(
+            kinds(src),
+            vec![Newline, Newline, Ident("a".to_string()), Eof]
+        );
+
+        let comments: Vec<_> = tokenize(src, false)
+            .unwrap()
+            .1
+            .into_iter()
+            .map(|c| (c.text, c.span.start.line, c.span.start.column))
+            .collect();
+        assert_eq!(
+            comments,
+            vec![
+                (" var.a".to_string(), 1, 2),
+                (" var.b".to_string(), 2, 3),
+                (" var.c ".to_string(), 3, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heredoc_tokens() {
+        assert_eq!(
+            kinds("a = <<-EOT\n  hi ${b}\n  EOT\n"),
+            vec![
+                Ident("a".to_string()),
+                Equal,
+                OHeredoc,
+                TemplateLit("  hi ".to_string()),
+                TemplateInterp,
+                Ident("b".to_string()),
+                TemplateSeqEnd,
+                TemplateLit("\n".to_string()),
+                CHeredoc,
+                Newline,
+                Eof,
+            ]
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! A small HCL2 front-end: just enough of the native syntax to build a
+//! syntax tree of bodies, blocks, attributes, expressions and templates.
+//! The tree keeps more than the checks currently look at.
+
+mod ast;
+mod json;
+mod lexer;
+mod parser;
+
+use std::fmt;
+
+pub use ast::*;
+pub use json::parse_json;
+pub use parser::{parse, parse_expression};
+
+/// A position in the source text. Lines and columns are 1-based, columns
+/// count characters rather than bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pos {
+    pub byte: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Pos {
+    pub fn start() -> Pos {
+        Pos {
+            byte: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// The position right after `text`, if `text` starts at this position.
+    pub fn advance(self, text: &str) -> Pos {
+        text.chars().fold(self, |pos, c| {
+            if c == '\n' {
+                Pos {
+                    byte: pos.byte + 1,
+                    line: pos.line + 1,
+                    column: 1,
+                }
+            } else {
+                Pos {
+                    byte: pos.byte + c.len_utf8(),
+                    column: pos.column + 1,
+                    ..pos
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: Pos,
+    pub end: Pos,
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub message: String,
+    pub pos: Pos,
+}
+
+impl Error {
+    fn new(message: impl Into<String>, pos: Pos) -> Error {
+        Error {
+            message: message.into(),
+            pos,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.pos.line, self.pos.column, self.message)
+    }
+}
@@ -0,0 +1,683 @@
+use super::ast::*;
+use super::lexer::{tokenize, Token, TokenKind};
+use super::{Error, Pos, Span};
+
+/// Parses a whole file in the native syntax.
+pub fn parse(src: &str) -> Result<Document, Error> {
+    let (tokens, comments) = tokenize(src, false)?;
+    let mut parser = Parser::new(tokens, false);
+    let body = parser.parse_body(false)?;
+    Ok(Document { body, comments })
+}
+
+/// Parses a JSON document as a single expression. JSON values are a subset
+/// of HCL expressions, and JSON strings are read as templates, the same way
+/// Terraform reads them.
+pub fn parse_json_value(src: &str) -> Result<Expression, Error> {
+    parse_single_expression(src, true)
+}
+
+/// Parses a standalone expression, such as a type constraint given as a
+/// string in the JSON syntax.
+pub fn parse_expression(src: &str) -> Result<Expression, Error> {
+    parse_single_expression(src, false)
+}
+
+fn parse_single_expression(src: &str, json: bool) -> Result<Expression, Error> {
+    let (tokens, _) = tokenize(src, json)?;
+    let mut parser = Parser::new(tokens, json);
+    parser.with_newlines(false, |p| {
+        let value = p.parse_expression()?;
+        p.expect(TokenKind::Eof, "end of file")?;
+        Ok(value)
+    })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    prev_end: Pos,
+    /// In JSON, newlines never separate object items
+    json: bool,
+    /// Whether newlines are significant in the current context. They end
+    /// attributes and object items, but are ignored inside brackets,
+    /// parentheses and template interpolations.
+    newlines: Vec<bool>,
+}
+
+fn binary_operator(kind: &TokenKind) -> Option<(Operator, u8)> {
+    let op = match kind {
+        TokenKind::Or => (Operator::Or, 1),
+        TokenKind::And => (Operator::And, 2),
+        TokenKind::EqualOp => (Operator::Equal, 3),
+        TokenKind::NotEqual => (Operator::NotEqual, 3),
+        TokenKind::Lt => (Operator::Less, 4),
+        TokenKind::Le => (Operator::LessOrEqual, 4),
+        TokenKind::Gt => (Operator::Greater, 4),
+        TokenKind::Ge => (Operator::GreaterOrEqual, 4),
+        TokenKind::Plus => (Operator::Add, 5),
+        TokenKind::Minus => (Operator::Subtract, 5),
+        TokenKind::Star => (Operator::Multiply, 6),
+        TokenKind::Slash => (Operator::Divide, 6),
+        TokenKind::Percent => (Operator::Modulo, 6),
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn is_keyword(kind: &TokenKind, keyword: &str) -> bool {
+    match kind {
+        TokenKind::Ident(ident) => ident == keyword,
+        _ => false,
+    }
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>, json: bool) -> Parser {
+        Parser {
+            tokens,
+            pos: 0,
+            prev_end: Pos::start(),
+            json,
+            newlines: vec![true],
+        }
+    }
+
+    fn skip_ignored_newlines(&mut self) {
+        if self.newlines.last() == Some(&false) {
+            while self.tokens[self.pos].kind == TokenKind::Newline {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn peek(&mut self) -> &Token {
+        self.skip_ignored_newlines();
+        &self.tokens[self.pos]
+    }
+
+    fn peek_kind(&mut self) -> TokenKind {
+        self.peek().kind.clone()
+    }
+
+    /// Kind of the token after the next one, without skipping newlines.
+    fn peek_second(&mut self) -> TokenKind {
+        self.skip_ignored_newlines();
+        self.tokens
+            .get(self.pos + 1)
+            .map_or(TokenKind::Eof, |t| t.kind.clone())
+    }
+
+    fn next(&mut self) -> Token {
+        self.skip_ignored_newlines();
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        self.prev_end = token.span.end;
+        token
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &str) -> Result<Token, Error> {
+        let token = self.next();
+        if token.kind == kind {
+            Ok(token)
+        } else {
+            Err(unexpected(&token, what))
+        }
+    }
+
+    fn expect_ident(&mut self, what: &str) -> Result<String, Error> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Ident(ident) => Ok(ident),
+            _ => Err(unexpected(&token, what)),
+        }
+    }
+
+    fn span_from(&self, start: Pos) -> Span {
+        Span {
+            start,
+            end: self.prev_end,
+        }
+    }
+
+    fn expr(&self, kind: ExprKind, start: Pos) -> Expression {
+        Expression {
+            kind,
+            span: self.span_from(start),
+        }
+    }
+
+    fn with_newlines<T>(
+        &mut self,
+        significant: bool,
+        f: impl FnOnce(&mut Parser) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        self.newlines.push(significant);
+        let result = f(self);
+        self.newlines.pop();
+        result
+    }
+
+    fn parse_body(&mut self, in_block: bool) -> Result<Body, Error> {
+        let mut body = Body::default();
+        loop {
+            let token = self.peek().clone();
+            match token.kind {
+                TokenKind::Newline => {
+                    self.next();
+                }
+                TokenKind::Eof if !in_block => break,
+                TokenKind::RBrace if in_block => break,
+                TokenKind::Ident(_) => body.structures.push(self.parse_structure()?),
+                _ => return Err(unexpected(&token, "attribute or block")),
+            }
+        }
+        Ok(body)
+    }
+
+    fn parse_structure(&mut self) -> Result<Structure, Error> {
+        let ident = self.next();
+        let start = ident.span.start;
+        let name = match ident.kind {
+            TokenKind::Ident(name) => name,
+            _ => return Err(unexpected(&ident, "identifier")),
+        };
+
+        if self.peek_kind() == TokenKind::Equal {
+            self.next();
+            let expr = self.parse_expression()?;
+            self.end_of_item()?;
+            return Ok(Structure::Attribute(Attribute {
+                name,
+                expr,
+                span: self.span_from(start),
+            }));
+        }
+
+        let mut labels = vec![];
+        loop {
+            let token = self.next();
+            match token.kind {
+                TokenKind::Ident(label) => labels.push(label),
+                TokenKind::OQuote => {
+                    let label = match self.next().kind {
+                        TokenKind::TemplateLit(label) => {
+                            self.expect(TokenKind::CQuote, "closing quote")?;
+                            label
+                        }
+                        TokenKind::CQuote => String::new(),
+                        _ => {
+                            return Err(Error::new(
+                                "block labels must be literal strings",
+                                token.span.start,
+                            ))
+                        }
+                    };
+                    labels.push(label);
+                }
+                TokenKind::LBrace => break,
+                _ => return Err(unexpected(&token, "block label or opening brace")),
+            }
+        }
+
+        let body = self.with_newlines(true, |p| p.parse_body(true))?;
+        self.expect(TokenKind::RBrace, "closing brace")?;
+        let span = self.span_from(start);
+        self.end_of_item()?;
+        Ok(Structure::Block(Block {
+            ident: name,
+            labels,
+            body,
+            span,
+        }))
+    }
+
+    fn end_of_item(&mut self) -> Result<(), Error> {
+        match self.peek_kind() {
+            TokenKind::Newline => {
+                self.next();
+                Ok(())
+            }
+            TokenKind::Eof | TokenKind::RBrace => Ok(()),
+            _ => {
+                let token = self.next();
+                Err(unexpected(&token, "newline"))
+            }
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression, Error> {
+        let start = self.peek().span.start;
+        let cond = self.parse_binary(1)?;
+        if self.peek_kind() != TokenKind::Question {
+            return Ok(cond);
+        }
+        self.next();
+        let t = self.parse_expression()?;
+        self.expect(TokenKind::Colon, "colon")?;
+        let e = self.parse_expression()?;
+        Ok(self.expr(
+            ExprKind::Conditional(Box::new(cond), Box::new(t), Box::new(e)),
+            start,
+        ))
+    }
+
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expression, Error> {
+        let start = self.peek().span.start;
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let (op, precedence) = match binary_operator(&self.peek_kind()) {
+                Some((op, precedence)) if precedence >= min_precedence => (op, precedence),
+                _ => break,
+            };
+            self.next();
+            let rhs = self.parse_binary(precedence + 1)?;
+            lhs = self.expr(ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)), start);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, Error> {
+        let start = self.peek().span.start;
+        let op = match self.peek_kind() {
+            TokenKind::Bang => Operator::Not,
+            TokenKind::Minus => Operator::Negate,
+            _ => {
+                let primary = self.parse_primary()?;
+                return self.parse_postfix(primary, start);
+            }
+        };
+        self.next();
+        let operand = self.parse_unary()?;
+        Ok(self.expr(ExprKind::Unary(op, Box::new(operand)), start))
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, Error> {
+        let token = self.next();
+        let start = token.span.start;
+        let kind = match token.kind {
+            TokenKind::Number(n) => ExprKind::Number(n),
+            TokenKind::Ident(ident) => match ident.as_str() {
+                "true" => ExprKind::Bool(true),
+                "false" => ExprKind::Bool(false),
+                "null" => ExprKind::Null,
+                _ if self.peek_kind() == TokenKind::LParen => {
+                    self.next();
+                    return self.with_newlines(false, |p| p.parse_call(ident, start));
+                }
+                _ => ExprKind::Variable(ident),
+            },
+            TokenKind::LParen => {
+                let inner = self.with_newlines(false, |p| {
+                    let inner = p.parse_expression()?;
+                    p.expect(TokenKind::RParen, "closing parenthesis")?;
+                    Ok(inner)
+                })?;
+                ExprKind::Parens(Box::new(inner))
+            }
+            TokenKind::LBrack => {
+                return self.with_newlines(false, |p| p.parse_tuple(start));
+            }
+            TokenKind::LBrace => {
+                let significant = !self.json;
+                return self.with_newlines(significant, |p| p.parse_object(start));
+            }
+            TokenKind::OQuote => ExprKind::Template(self.parse_template(TokenKind::CQuote)?),
+            TokenKind::OHeredoc => ExprKind::Template(self.parse_template(TokenKind::CHeredoc)?),
+            _ => return Err(unexpected(&token, "expression")),
+        };
+        Ok(self.expr(kind, start))
+    }
+
+    fn parse_postfix(&mut self, mut expr: Expression, start: Pos) -> Result<Expression, Error> {
+        loop {
+            match self.peek_kind() {
+                TokenKind::Dot => {
+                    self.next();
+                    let token = self.next();
+                    let kind = match token.kind {
+                        TokenKind::Ident(name) => ExprKind::GetAttr(Box::new(expr), name),
+                        TokenKind::Number(n) => {
+                            let key = Expression {
+                                kind: ExprKind::Number(n),
+                                span: token.span,
+                            };
+                            ExprKind::Index(Box::new(expr), Box::new(key))
+                        }
+                        TokenKind::Star => ExprKind::Splat(Box::new(expr)),
+                        _ => return Err(unexpected(&token, "attribute name")),
+                    };
+                    expr = self.expr(kind, start);
+                }
+                TokenKind::LBrack => {
+                    self.next();
+                    let kind = self.with_newlines(false, |p| {
+                        if p.peek_kind() == TokenKind::Star && p.peek_second() == TokenKind::RBrack
+                        {
+                            p.next();
+                            p.next();
+                            return Ok(ExprKind::Splat(Box::new(expr)));
+                        }
+                        let key = p.parse_expression()?;
+                        p.expect(TokenKind::RBrack, "closing bracket")?;
+                        Ok(ExprKind::Index(Box::new(expr), Box::new(key)))
+                    })?;
+                    expr = self.expr(kind, start);
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn parse_call(&mut self, name: String, start: Pos) -> Result<Expression, Error> {
+        let mut args = vec![];
+        let mut expand_final = false;
+        while self.peek_kind() != TokenKind::RParen {
+            args.push(self.parse_expression()?);
+            match self.peek_kind() {
+                TokenKind::Comma => {
+                    self.next();
+                }
+                TokenKind::Ellipsis => {
+                    self.next();
+                    expand_final = true;
+                    break;
+                }
+                _ => break,
+            }
+        }
+        self.expect(TokenKind::RParen, "closing parenthesis")?;
+        let kind = ExprKind::FunctionCall {
+            name,
+            args,
+            expand_final,
+        };
+        Ok(self.expr(kind, start))
+    }
+
+    fn parse_tuple(&mut self, start: Pos) -> Result<Expression, Error> {
+        if is_keyword(&self.peek_kind(), "for") {
+            return self.parse_for(start, TokenKind::RBrack);
+        }
+        let mut items = vec![];
+        while self.peek_kind() != TokenKind::RBrack {
+            items.push(self.parse_expression()?);
+            if self.peek_kind() != TokenKind::Comma {
+                break;
+            }
+            self.next();
+        }
+        self.expect(TokenKind::RBrack, "closing bracket")?;
+        Ok(self.expr(ExprKind::Tuple(items), start))
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek_kind() == TokenKind::Newline {
+            self.next();
+        }
+    }
+
+    fn parse_object(&mut self, start: Pos) -> Result<Expression, Error> {
+        self.skip_newlines();
+        if is_keyword(&self.peek_kind(), "for") {
+            if let TokenKind::Ident(_) = self.peek_second() {
+                return self.with_newlines(false, |p| p.parse_for(start, TokenKind::RBrace));
+            }
+        }
+        let mut items = vec![];
+        loop {
+            self.skip_newlines();
+            if self.peek_kind() == TokenKind::RBrace {
+                break;
+            }
+            let key = self.parse_expression()?;
+            let token = self.next();
+            if token.kind != TokenKind::Equal && token.kind != TokenKind::Colon {
+                return Err(unexpected(&token, "equals sign or colon"));
+            }
+            let value = self.parse_expression()?;
+            items.push(ObjectItem { key, value });
+            match self.peek_kind() {
+                TokenKind::Comma | TokenKind::Newline => {
+                    self.next();
+                }
+                TokenKind::RBrace => {}
+                _ => {
+                    let token = self.next();
+                    return Err(unexpected(&token, "comma or newline"));
+                }
+            }
+        }
+        self.expect(TokenKind::RBrace, "closing brace")?;
+        Ok(self.expr(ExprKind::Object(items), start))
+    }
+
+    fn parse_for_header(&mut self) -> Result<(Option<String>, String, Expression), Error> {
+        self.expect_ident("for")?;
+        let first = self.expect_ident("iterator name")?;
+        let (key_var, value_var) = if self.peek_kind() == TokenKind::Comma {
+            self.next();
+            (Some(first), self.expect_ident("iterator name")?)
+        } else {
+            (None, first)
+        };
+        let token = self.next();
+        if !is_keyword(&token.kind, "in") {
+            return Err(unexpected(&token, "in"));
+        }
+        let collection = self.parse_expression()?;
+        Ok((key_var, value_var, collection))
+    }
+
+    fn parse_for(&mut self, start: Pos, closing: TokenKind) -> Result<Expression, Error> {
+        let (key_var, value_var, collection) = self.parse_for_header()?;
+        self.expect(TokenKind::Colon, "colon")?;
+        let first = self.parse_expression()?;
+        let mut grouping = false;
+        let (key, value) = if closing == TokenKind::RBrace {
+            self.expect(TokenKind::FatArrow, "=>")?;
+            let value = self.parse_expression()?;
+            if self.peek_kind() == TokenKind::Ellipsis {
+                self.next();
+                grouping = true;
+            }
+            (Some(first), value)
+        } else {
+            (None, first)
+        };
+        let condition = if is_keyword(&self.peek_kind(), "if") {
+            self.next();
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        self.expect(closing, "end of for expression")?;
+        let for_expr = ForExpr {
+            key_var,
+            value_var,
+            collection,
+            key,
+            value,
+            condition,
+            grouping,
+        };
+        Ok(self.expr(ExprKind::For(Box::new(for_expr)), start))
+    }
+
+    fn parse_template(&mut self, closing: TokenKind) -> Result<Vec<TemplatePart>, Error> {
+        let mut parts = vec![];
+        loop {
+            let token = self.next();
+            match token.kind {
+                TokenKind::TemplateLit(lit) => parts.push(TemplatePart::Literal(lit)),
+                TokenKind::TemplateInterp => {
+                    let expr = self.with_newlines(false, |p| {
+                        let expr = p.parse_expression()?;
+                        p.expect(TokenKind::TemplateSeqEnd, "end of interpolation")?;
+                        Ok(expr)
+                    })?;
+                    parts.push(TemplatePart::Interpolation(expr));
+                }
+                TokenKind::TemplateControl => {
+                    let directive = self.with_newlines(false, |p| {
+                        let directive = p.parse_directive()?;
+                        p.expect(TokenKind::TemplateSeqEnd, "end of directive")?;
+                        Ok(directive)
+                    })?;
+                    parts.push(TemplatePart::Directive(directive));
+                }
+                ref kind if *kind == closing => return Ok(parts),
+                _ => return Err(unexpected(&token, "end of template")),
+            }
+        }
+    }
+
+    fn parse_directive(&mut self) -> Result<Directive, Error> {
+        let token = self.peek().clone();
+        let keyword = match &token.kind {
+            TokenKind::Ident(keyword) => keyword.clone(),
+            _ => return Err(unexpected(&token, "template directive")),
+        };
+        let directive = match keyword.as_str() {
+            "if" => {
+                self.next();
+                Directive::If(self.parse_expression()?)
+            }
+            "else" => {
+                self.next();
+                Directive::Else
+            }
+            "endif" => {
+                self.next();
+                Directive::EndIf
+            }
+            "endfor" => {
+                self.next();
+                Directive::EndFor
+            }
+            "for" => {
+                let (key_var, value_var, collection) = self.parse_for_header()?;
+                Directive::For {
+                    key_var,
+                    value_var,
+                    collection,
+                }
+            }
+            _ => return Err(unexpected(&token, "template directive")),
+        };
+        Ok(directive)
+    }
+}
+
+fn unexpected(token: &Token, expected: &str) -> Error {
+    let found = match &token.kind {
+        TokenKind::Eof => "end of file".to_string(),
+        TokenKind::Newline => "newline".to_string(),
+        TokenKind::Ident(ident) => format!("`{}`", ident),
+        kind => format!("{:?}", kind),
+    };
+    Error::new(
+        format!("expected {}, found {}", expected, found),
+        token.span.start,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_and_attributes() {
+        let body = parse(
+            r#"
+            resource "aws_instance" web {
+              ami = "${data.aws_ami.ubuntu.id}"
+
+              tags {
+                Name = var.instance_name
+              }
+            }
+            "#,
+        )
+        .unwrap()
+        .body;
+
+        let block = body.blocks().next().unwrap();
+        assert_eq!(block.ident, "resource");
+        assert_eq!(block.labels, vec!["aws_instance", "web"]);
+        assert_eq!(block.body.attribute("ami").unwrap().name, "ami");
+        assert_eq!(block.body.blocks_of("tags").count(), 1);
+    }
+
+    #[test]
+    fn test_traversals() {
+        let body = parse(
+            r#"
+            a = var.list[0].name
+            b = "${upper(var.x)}-${local.y["k"]}"
+            c = [for k, v in var.m : "${k}=${v}" if v != data.t.n.id]
+            d = {
+              e = var.z ? 1 : -2
+              "f" = module.m.out[*].id
+            }
+            "#,
+        )
+        .unwrap()
+        .body;
+
+        let found: Vec<_> = body
+            .traversals()
+            .into_iter()
+            .map(|t| format!("{}.{}", t.root, t.attrs.join(".")))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                "var.list",
+                "var.x",
+                "local.y.k",
+                "var.m",
+                "k.",
+                "v.",
+                "v.",
+                "data.t.n.id",
+                "var.z",
+                "module.m.out",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heredoc_and_directives() {
+        let body = parse(
+            "policy = <<EOF\n%{ for s in var.subnets ~}\n${s}\n%{ endfor ~}\nEOF\nnext = 1\n",
+        )
+        .unwrap()
+        .body;
+        let names: Vec<_> = body.attributes().map(|a| a.name.clone()).collect();
+        assert_eq!(names, vec!["policy", "next"]);
+        assert_eq!(body.traversals()[0].root, "var");
+    }
+
+    #[test]
+    fn test_error_position() {
+        let err = parse("a = 1\nb = = 2\n").unwrap_err();
+        assert_eq!((err.pos.line, err.pos.column), (2, 5));
+    }
+
+    #[test]
+    fn test_bom_and_provider_functions() {
+        let body = parse("\u{feff}arn = provider::aws::arn_parse(var.arn)\n")
+            .unwrap()
+            .body;
+        let arn = body.attribute("arn").unwrap();
+        assert_eq!(arn.span.start.column, 1);
+        match &arn.expr.kind {
+            ExprKind::FunctionCall { name, .. } => assert_eq!(name, "provider::aws::arn_parse"),
+            kind => panic!("unexpected {:?}", kind),
+        }
+        assert_eq!(body.traversals()[0].root, "var");
+    }
+}
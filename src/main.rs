@@ -1,149 +1,96 @@
-use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process;
 
 use clap::{App, Arg};
-use glob::glob;
-use itertools::Itertools;
-use regex::Regex;
 
 #[macro_use]
 extern crate lazy_static;
 
-lazy_static! {
-    static ref DEFINTION_REGEX: Regex = Regex::new(r#"variable\s+"([\w_]+)"\s+\{"#).unwrap();
-    static ref VALUE_REGEX: Regex = Regex::new(r#"([\w_]+)\s+=\s+(.*)"#).unwrap();
-    static ref USE_REGEX: Regex = Regex::new(r#"var\.([\w_]+)"#).unwrap();
-}
+mod env;
+mod environment;
+mod file;
+mod findings;
+mod hcl;
+mod module;
+mod scripts;
+mod types;
 
-#[derive(Debug, Clone, Copy)]
-enum EntryType {
-    Definition,
-    Use,
-    Value,
-}
+use environment::Environment;
+use findings::{Finding, FindingKind};
+use module::Module;
 
-impl EntryType {
-    fn regex(&self) -> &Regex {
-        match self {
-            Self::Definition => &DEFINTION_REGEX,
-            Self::Use => &USE_REGEX,
-            Self::Value => &VALUE_REGEX,
-        }
+fn validate_and_get_path(wd: &str) -> Result<&Path, String> {
+    let wd_path = Path::new(wd);
+    if !wd_path.exists() {
+        return Err(format!("Path {} does not exists", wd));
     }
-}
 
-#[derive(Debug)]
-struct Variable {
-    entry_type: EntryType,
-    name: String,
-    at: String,
-}
+    if !wd_path.is_dir() {
+        return Err(format!("{} is not a directory", wd));
+    }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum FileType {
-    Source,
-    Vars,
+    Ok(wd_path)
 }
 
-impl FileType {
-    fn ext(self) -> String {
-        match self {
-            FileType::Source => "tf".to_string(),
-            FileType::Vars => "tfvars".to_string(),
+fn report_unsued(unused: &[Finding]) {
+    for f in unused {
+        let mut message = format!("{} {}", f.kind.prefix(), f.var.name);
+        if let Some(note) = &f.note {
+            message.push_str(&format!(" ({})", note));
         }
+        println!(
+            "{}: {}: {}",
+            f.var.location(),
+            f.kind.severity().label(),
+            message
+        );
     }
 }
 
-#[derive(Debug)]
-struct File {
-    file_type: FileType,
-    path: String,
-    contents: String,
-}
-
-impl File {
-    fn files_in(dir: &Path) -> Result<Vec<Result<File, String>>, String> {
-        let mut files = Self::get_files(FileType::Source, dir)?;
-        files.extend(Self::get_files(FileType::Vars, dir)?);
-        Ok(files)
-    }
-
-    fn get_files(file_type: FileType, dir: &Path) -> Result<Vec<Result<File, String>>, String> {
-        let path_buf = dir.join(format!("*.{}", file_type.ext()));
-
-        let g = match path_buf.as_path().to_str() {
-            Some(glob_path) => glob_path.to_string(),
-            None => return Err("Failed to construct glob expression".to_string()),
-        };
-
-        let file_paths = match glob(&g) {
-            Ok(files) => files.filter_map(Result::ok),
-            Err(err) => return Err(err.to_string()),
-        };
+fn report_matrix(matrix: &environment::Matrix) {
+    let width = matrix
+        .rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(Some("variable".len()))
+        .max()
+        .unwrap_or(0);
+    let widths: Vec<_> = matrix
+        .environments
+        .iter()
+        .map(|e| e.len().max("default".len()))
+        .collect();
 
-        let files = file_paths
-            .map(|path| {
-                let path_str = path
-                    .clone()
-                    .into_os_string()
-                    .into_string()
-                    .unwrap_or_else(|_| "unknown path".to_string());
-                if let Ok(contents) = fs::read_to_string(path) {
-                    let file = File {
-                        file_type,
-                        path: path_str.to_string(),
-                        contents,
-                    };
-                    Ok(file)
-                } else {
-                    Err(format!("Error: could not read file {}", path_str))
-                }
-            })
-            .collect();
-        Ok(files)
+    let mut header = format!("{:width$}", "variable", width = width);
+    for (env, w) in matrix.environments.iter().zip(&widths) {
+        header.push_str(&format!("  {:w$}", env, w = w));
     }
-
-    fn get_var_entries(&self, entry_type: EntryType) -> Vec<Variable> {
-        entry_type
-            .regex()
-            .captures_iter(&self.contents)
-            .filter(|cap| cap.len() > 1)
-            .map(|cap| Variable {
-                name: cap[1].to_string(),
-                at: self.path.clone(),
-                entry_type,
-            })
-            .collect()
+    println!("{}", header.trim_end());
+    for (name, cells) in &matrix.rows {
+        let mut line = format!("{:width$}", name, width = width);
+        for (cell, w) in cells.iter().zip(&widths) {
+            line.push_str(&format!("  {:w$}", cell.label(), w = w));
+        }
+        println!("{}", line.trim_end());
     }
 }
 
-fn validate_and_get_path(wd: &str) -> Result<Box<&Path>, String> {
-    let wd_path = Path::new(wd);
-    if !wd_path.exists() {
-        return Err(format!("Path {} does not exists", wd));
+fn report_var_files(module: &Module) {
+    println!("Loaded without flags, in this order:");
+    for file in module.auto_loaded_files() {
+        println!("  {}", file.path);
     }
-
-    if !wd_path.is_dir() {
-        return Err(format!("{} is not a directory", wd));
-    }
-
-    Ok(Box::new(wd_path))
-}
-
-fn report_unsued(unused: &[&Variable]) {
-    let by_file = unused.iter().group_by(|v| &v.at);
-    for (file, vars) in &by_file {
-        println!("In {}:", file);
-        for v in vars {
-            let prefix = match v.entry_type {
-                EntryType::Definition => "Unused definition",
-                EntryType::Value => "Unused value for",
-                _ => unreachable!(),
-            };
-            println!(" * {} {}", prefix, v.name);
+    if !module.var_files.is_empty() {
+        println!("Loaded with --var-file, in this order:");
+        for path in &module.var_files {
+            println!("  {}", path);
         }
-        println!();
+    }
+    println!("Only loaded with -var-file:");
+    for file in module.files.iter().filter(|f| {
+        f.file_type.is_vars() && !module.is_auto_loaded(f) && !module.var_files.contains(&f.path)
+    }) {
+        println!("  {}", file.path);
     }
 }
 
@@ -158,6 +105,58 @@ fn main() {
                 .required(false)
                 .index(1),
         )
+        .arg(
+            Arg::with_name("recursive")
+                .short("r")
+                .long("recursive")
+                .help("Check every module directory under INPUT separately"),
+        )
+        .arg(
+            Arg::with_name("var-file")
+                .long("var-file")
+                .value_name("PATH")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Also check values from this var file, like terraform's -var-file"),
+        )
+        .arg(
+            Arg::with_name("no-auto-var-files")
+                .long("no-auto-var-files")
+                .help("Ignore var files in the module directory"),
+        )
+        .arg(
+            Arg::with_name("env-vars")
+                .long("env-vars")
+                .help("Also check values from TF_VAR_ environment variables"),
+        )
+        .arg(
+            Arg::with_name("env-file")
+                .long("env-file")
+                .value_name("PATH")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Also check values from TF_VAR_ variables in this dotenv file"),
+        )
+        .arg(
+            Arg::with_name("scan-scripts")
+                .long("scan-scripts")
+                .help(
+                    "Also check -var and -var-file flags in shell scripts, Makefiles and CI YAML under INPUT",
+                ),
+        )
+        .arg(
+            Arg::with_name("list-var-files")
+                .long("list-var-files")
+                .help("List the var files Terraform would load without flags, and exit"),
+        )
+        .arg(
+            Arg::with_name("environment")
+                .long("environment")
+                .value_name("NAME=PATHS")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Check values per environment, each with its own comma-separated var files"),
+        )
         .get_matches();
 
     let working_dir = matches.value_of("INPUT").unwrap_or(".");
@@ -166,109 +165,143 @@ fn main() {
         process::exit(1)
     });
 
-    let files: &Vec<_> = &File::files_in(&wd_path)
-        .unwrap_or_else(|e| {
+    let recursive = matches.is_present("recursive");
+    let module_dirs = if recursive {
+        module::module_dirs(wd_path).unwrap_or_else(|e| {
             println!("{}", e);
             process::exit(1);
         })
-        .into_iter()
-        .filter_map(|f| match f {
-            Ok(f) => Some(f),
+    } else {
+        vec![wd_path.to_path_buf()]
+    };
+
+    let var_files: Vec<PathBuf> = matches
+        .values_of("var-file")
+        .map(|paths| paths.map(PathBuf::from).collect())
+        .unwrap_or_default();
+    let auto_var_files = !matches.is_present("no-auto-var-files");
+
+    // Files that can't be read or parsed are reported and skipped, but
+    // still fail the run
+    let mut has_errors = false;
+    let mut extra_values = vec![];
+    if matches.is_present("env-vars") {
+        extra_values.extend(env::process_values());
+    }
+    for path in matches.values_of("env-file").into_iter().flatten() {
+        match env::read_env_file(Path::new(path)) {
+            Ok(values) => extra_values.extend(values),
             Err(e) => {
                 println!("{}", e);
-                None
+                has_errors = true;
             }
-        })
-        .collect();
-
-    let definitions: Vec<_> = files
-        .iter()
-        .filter(|f| f.file_type == FileType::Source)
-        .map(|f| f.get_var_entries(EntryType::Definition))
-        .flatten()
-        .collect();
-
-    let uses: Vec<_> = files
-        .iter()
-        .filter(|f| f.file_type == FileType::Source)
-        .map(|f| f.get_var_entries(EntryType::Use))
-        .flatten()
-        .collect();
-
-    let values: Vec<_> = files
-        .iter()
-        .filter(|f| f.file_type == FileType::Vars)
-        .map(|f| f.get_var_entries(EntryType::Value))
-        .flatten()
-        .collect();
-
-    let unused: Vec<_> = definitions
-        .iter()
-        .filter(|def| uses.iter().find(|inst| inst.name == def.name).is_none())
-        .collect();
-
-    let unused_vals: Vec<_> = values
-        .iter()
-        .filter(|val| {
-            definitions
-                .iter()
-                .find(|def| def.name == val.name)
-                .is_none()
-        })
-        .collect();
-
-    report_unsued(&unused);
-    report_unsued(&unused_vals);
-
-    if !&unused.is_empty() || !&unused_vals.is_empty() {
-        process::exit(1);
+        }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_tfvars_pattern() {
-        let re = &VALUE_REGEX;
-        let test_string = r#"
-        one = 42
-        two = "booooring"
-    two_and_a_half = "now it's getting interesting"
 
-          three_times_fourty_two = true
-        "#;
-        assert!(re.is_match(test_string));
-        for cap in re.captures_iter(test_string) {
-            //assert!(&cap[1] == "surprisingly_important_variable")
-            println!("MATCH: {} -> {}", &cap[1], &cap[2]);
+    // Scripts pass their flags to the module they run in, which is the
+    // closest module directory at or above them
+    let scripts = if matches.is_present("scan-scripts") {
+        let flags = scripts::scan(wd_path).unwrap_or_else(|e| {
+            println!("{}", e);
+            process::exit(1);
+        });
+        let script_modules = module::module_dirs(wd_path).unwrap_or_else(|e| {
+            println!("{}", e);
+            process::exit(1);
+        });
+        Some((flags, script_modules))
+    } else {
+        None
+    };
+
+    let mut environments = vec![];
+    for spec in matches.values_of("environment").into_iter().flatten() {
+        let (name, paths) = Environment::parse_spec(spec).unwrap_or_else(|e| {
+            println!("{}", e);
+            process::exit(1);
+        });
+        let (environment, errors) = Environment::load(name, &paths);
+        for e in &errors {
+            println!("{}", e);
         }
+        has_errors |= !errors.is_empty();
+        environments.push(environment);
     }
 
-    #[test]
-    fn test_variable_pattern() {
-        let re = &DEFINTION_REGEX;
-        let test_string = r#"
-        variable "surprisingly_important_variable" {
-            default = 42
+    let mut modules = vec![];
+    for dir in module_dirs {
+        let (mut module, mut errors) = Module::load(&dir).unwrap_or_else(|e| {
+            println!("{}", e);
+            process::exit(1);
+        });
+        errors.extend(module.set_var_files(&var_files, auto_var_files));
+        module.extra_values = extra_values.clone();
+        if let Some((flags, script_modules)) = &scripts {
+            let flags = flags.of_module(&dir, script_modules, wd_path);
+            module.extra_values.extend(flags.values);
+            let script_var_files: Vec<_> = flags
+                .var_files
+                .iter()
+                .flat_map(|f| scripts::ScriptFlags::resolve_var_file(f, wd_path))
+                .collect();
+            errors.extend(module.add_script_var_files(&script_var_files));
         }
-        "#;
-        assert!(re.is_match(test_string));
-        for cap in re.captures_iter(test_string) {
-            assert!(&cap[1] == "surprisingly_important_variable")
+        for e in &errors {
+            println!("{}", e);
         }
+        has_errors |= !errors.is_empty();
+        modules.push(module);
     }
 
-    #[test]
-    fn test_variable_use_pattern() {
-        let test_string = r#"something = "${foo(var.very_important_variable)}""#;
-        let re = &USE_REGEX;
+    if matches.is_present("list-var-files") {
+        for module in &modules {
+            if recursive {
+                println!("In module {}:", module.dir.display());
+            }
+            report_var_files(module);
+            if recursive {
+                println!();
+            }
+        }
+        if has_errors {
+            process::exit(1);
+        }
+        return;
+    }
 
-        assert!(re.is_match(test_string));
+    let mut has_findings = false;
+    for module in &modules {
+        let mut findings = findings::analyse(module);
+        findings.extend(findings::unused_outputs(module, &modules));
+        if !environments.is_empty() {
+            // Pooled value checks would hide the differences between
+            // environments
+            findings.retain(|f| {
+                f.kind != FindingKind::UnusedValue && f.kind != FindingKind::RequiredNotSet
+            });
+            findings.extend(findings::analyse_environments(module, &environments));
+        }
+        has_findings |= !findings.is_empty();
+        if findings.is_empty() && environments.is_empty() {
+            continue;
+        }
 
-        for cap in re.captures_iter(test_string) {
-            assert!(&cap[1] == "very_important_variable");
+        if recursive {
+            println!("In module {}:", module.dir.display());
         }
+        report_unsued(&findings);
+        if !environments.is_empty() {
+            if !findings.is_empty() {
+                println!();
+            }
+            report_matrix(&environment::matrix(module, &environments));
+        }
+        if recursive {
+            println!();
+        }
+    }
+
+    if has_findings || has_errors {
+        process::exit(1);
     }
 }
@@ -0,0 +1,341 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file::{EntryType, File, FileType, ModuleCall, Variable};
+
+/// A directory of Terraform files, analysed as a whole.
+#[derive(Debug)]
+pub struct Module {
+    pub dir: PathBuf,
+    pub files: Vec<File>,
+    /// Values from outside var files: `TF_VAR_` environment variables and
+    /// `-var` flags
+    pub extra_values: Vec<Variable>,
+    /// Paths of the var files given with `--var-file`, in the order they
+    /// are loaded
+    pub var_files: Vec<String>,
+    /// Some source files couldn't be read or parsed, so the uses and
+    /// definitions in them are missing
+    pub incomplete: bool,
+}
+
+impl Module {
+    /// Loads every Terraform file in `dir`. Files that can't be read or
+    /// parsed are skipped and their errors returned alongside the module.
+    pub fn load(dir: &Path) -> Result<(Module, Vec<String>), String> {
+        let mut files = vec![];
+        let mut errors = vec![];
+        let mut incomplete = false;
+        for (file_type, file) in File::files_in(dir)? {
+            match file {
+                Ok(f) => files.push(f),
+                Err(e) => {
+                    incomplete |= file_type.is_source();
+                    errors.push(e);
+                }
+            }
+        }
+        let module = Module {
+            dir: dir.to_path_buf(),
+            files,
+            extra_values: vec![],
+            var_files: vec![],
+            incomplete,
+        };
+        Ok((module, errors))
+    }
+
+    /// Adds var files given with `--var-file`, and with `auto` unset drops
+    /// the ones found in the module directory. A file given twice counts
+    /// where it was given last. Returns the errors of files that can't be
+    /// read or parsed.
+    pub fn set_var_files(&mut self, var_files: &[PathBuf], auto: bool) -> Vec<String> {
+        if !auto {
+            self.files.retain(|f| !f.file_type.is_vars());
+        }
+        let mut errors = vec![];
+        for path in var_files {
+            match self.load_var_file(path) {
+                Ok(path) => {
+                    self.var_files.retain(|p| *p != path);
+                    self.var_files.push(path);
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        errors
+    }
+
+    /// Adds var files that scripts pass with `-var-file`. Which of them a
+    /// run uses, and in which order, is up to the script, so they are kept
+    /// out of the order of `loaded_var_files`.
+    pub fn add_script_var_files(&mut self, var_files: &[PathBuf]) -> Vec<String> {
+        var_files
+            .iter()
+            .filter_map(|path| self.load_var_file(path).err())
+            .collect()
+    }
+
+    /// Reads a var file unless it is loaded already, and returns the path
+    /// of the loaded file.
+    fn load_var_file(&mut self, path: &Path) -> Result<String, String> {
+        let canonical = fs::canonicalize(path).ok();
+        let loaded = self.files.iter().find(|f| {
+            f.file_type.is_vars()
+                && canonical.is_some()
+                && fs::canonicalize(&f.path).ok() == canonical
+        });
+        if let Some(f) = loaded {
+            return Ok(f.path.clone());
+        }
+        let file = File::read_var_file(path)?;
+        let path = file.path.clone();
+        self.files.push(file);
+        Ok(path)
+    }
+
+    /// Var files Terraform loads without flags, in the order it loads them.
+    /// Files also given with `--var-file` are loaded later, and left out.
+    pub fn auto_loaded_files(&self) -> Vec<&File> {
+        let mut files: Vec<_> = self
+            .files
+            .iter()
+            .filter(|f| self.is_auto_loaded(f) && !self.var_files.contains(&f.path))
+            .filter_map(|f| Some((f.auto_load_order()?, f)))
+            .collect();
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        files.into_iter().map(|(_, f)| f).collect()
+    }
+
+    /// Whether Terraform loads `file` without flags: it has the name of an
+    /// auto-loaded file and sits in the module directory, not in one that
+    /// `--var-file` or a script points into.
+    pub fn is_auto_loaded(&self, file: &File) -> bool {
+        let parent = match Path::new(&file.path).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let dir = fs::canonicalize(&self.dir).ok();
+        file.is_auto_loaded() && dir.is_some() && fs::canonicalize(parent).ok() == dir
+    }
+
+    /// Every var file in the order Terraform loads them: the auto-loaded
+    /// ones, then the ones given with `--var-file`. Later values override
+    /// earlier ones.
+    pub fn loaded_var_files(&self) -> Vec<&File> {
+        let mut files = self.auto_loaded_files();
+        files.extend(
+            self.var_files
+                .iter()
+                .filter_map(|path| self.files.iter().find(|f| f.path == *path)),
+        );
+        files
+    }
+
+    /// Entries of the given type from all files they can occur in. Values
+    /// from outside var files come last.
+    pub fn entries(&self, entry_type: EntryType) -> Vec<Variable> {
+        let from: fn(FileType) -> bool = match entry_type {
+            EntryType::Value => FileType::is_vars,
+            _ => FileType::is_source,
+        };
+        let mut entries: Vec<_> = self
+            .files
+            .iter()
+            .filter(|f| from(f.file_type))
+            .flat_map(|f| f.get_var_entries(entry_type))
+            .collect();
+        if let EntryType::Value = entry_type {
+            entries.extend(self.extra_values.iter().cloned());
+        }
+        entries
+    }
+
+    /// Whether any var file, environment variable or flag sets values.
+    pub fn has_values(&self) -> bool {
+        !self.extra_values.is_empty() || self.files.iter().any(|f| f.file_type.is_vars())
+    }
+
+    pub fn module_calls(&self) -> Vec<ModuleCall> {
+        self.files
+            .iter()
+            .filter(|f| f.file_type.is_source())
+            .flat_map(File::module_calls)
+            .collect()
+    }
+}
+
+/// Every directory under `root` (`root` included) that contains Terraform
+/// or Terragrunt configuration. `.terraform/` and other hidden directories
+/// are skipped.
+pub fn module_dirs(root: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut dirs = vec![];
+    collect_module_dirs(root, &mut dirs)?;
+    Ok(dirs)
+}
+
+/// Whether `dir` itself contains Terraform or Terragrunt configuration.
+pub fn is_module_dir(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|entries| {
+        entries
+            .filter_map(Result::ok)
+            .any(|e| is_config_file(&e.path()))
+    })
+}
+
+fn is_config_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    path.is_file()
+        && (name.ends_with(".tf") || name.ends_with(".tf.json") || name == "terragrunt.hcl")
+}
+
+fn collect_module_dirs(dir: &Path, dirs: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Error: could not read {}: {}", dir.display(), e))?;
+    let mut paths: Vec<_> = entries.filter_map(Result::ok).map(|e| e.path()).collect();
+    paths.sort();
+
+    if paths.iter().any(|p| is_config_file(p)) {
+        dirs.push(dir.to_path_buf());
+    }
+
+    for path in paths.iter().filter(|p| p.is_dir()) {
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if !hidden {
+            collect_module_dirs(path, dirs)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_loaded_files() {
+        let (module, _) = Module::load(Path::new("tests/fixtures/has_duplicates")).unwrap();
+        let files: Vec<_> = module
+            .auto_loaded_files()
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                "tests/fixtures/has_duplicates/terraform.tfvars",
+                "tests/fixtures/has_duplicates/a.auto.tfvars",
+                "tests/fixtures/has_duplicates/zones.auto.tfvars",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_var_files() {
+        let (mut module, _) = Module::load(Path::new("tests/fixtures/has_unused")).unwrap();
+        let errors = module.set_var_files(
+            &[
+                PathBuf::from("tests/fixtures/var_files/prod.tfvars"),
+                PathBuf::from("tests/fixtures/var_files/missing.tfvars"),
+            ],
+            false,
+        );
+        assert_eq!(
+            errors,
+            vec!["Error: could not read file tests/fixtures/var_files/missing.tfvars"]
+        );
+        let vars: Vec<_> = module
+            .files
+            .iter()
+            .filter(|f| f.file_type.is_vars())
+            .map(|f| f.path.as_str())
+            .collect();
+        assert_eq!(vars, vec!["tests/fixtures/var_files/prod.tfvars"]);
+    }
+
+    #[test]
+    fn test_auto_loaded_only_in_module_dir() {
+        let dir = Path::new("tests/fixtures/has_duplicates");
+        let (mut module, _) = Module::load(dir).unwrap();
+        let outside = [PathBuf::from("tests/fixtures/var_files/prod.auto.tfvars")];
+        assert!(module.add_script_var_files(&outside).is_empty());
+        let loaded = |module: &Module| -> Vec<String> {
+            module
+                .loaded_var_files()
+                .iter()
+                .map(|f| f.path.clone())
+                .collect()
+        };
+        assert_eq!(
+            loaded(&module),
+            vec![
+                "tests/fixtures/has_duplicates/terraform.tfvars",
+                "tests/fixtures/has_duplicates/a.auto.tfvars",
+                "tests/fixtures/has_duplicates/zones.auto.tfvars",
+            ]
+        );
+
+        // Given with --var-file, it loads after the module's own files
+        assert!(module.set_var_files(&outside, true).is_empty());
+        assert_eq!(
+            loaded(&module).last().map(String::as_str),
+            Some("tests/fixtures/var_files/prod.auto.tfvars")
+        );
+    }
+
+    #[test]
+    fn test_loaded_var_files() {
+        let dir = Path::new("tests/fixtures/has_duplicates");
+        let (mut module, _) = Module::load(dir).unwrap();
+        let errors = module.set_var_files(
+            &[
+                dir.join("./terraform.tfvars"),
+                PathBuf::from("tests/fixtures/var_files/prod.tfvars"),
+                dir.join("terraform.tfvars"),
+            ],
+            true,
+        );
+        assert!(errors.is_empty());
+        let files: Vec<_> = module
+            .loaded_var_files()
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                "tests/fixtures/has_duplicates/a.auto.tfvars",
+                "tests/fixtures/has_duplicates/zones.auto.tfvars",
+                "tests/fixtures/var_files/prod.tfvars",
+                "tests/fixtures/has_duplicates/terraform.tfvars",
+            ]
+        );
+        let var_files = module.files.iter().filter(|f| f.file_type.is_vars());
+        assert_eq!(var_files.count(), 4);
+
+        // Loaded already, and kept out of the order
+        let errors = module.add_script_var_files(&[
+            dir.join("a.auto.tfvars"),
+            PathBuf::from("tests/fixtures/var_files/prod.tfvars"),
+        ]);
+        assert!(errors.is_empty());
+        assert_eq!(module.loaded_var_files().len(), 4);
+        let var_files = module.files.iter().filter(|f| f.file_type.is_vars());
+        assert_eq!(var_files.count(), 4);
+    }
+
+    #[test]
+    fn test_module_dirs() {
+        let dirs = module_dirs(Path::new("tests/fixtures/monorepo")).unwrap();
+        assert_eq!(
+            dirs,
+            vec![
+                PathBuf::from("tests/fixtures/monorepo/app"),
+                PathBuf::from("tests/fixtures/monorepo/network"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! `-var` and `-var-file` flags of Terraform commands in shell scripts,
+//! Makefiles and CI configuration.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::glob;
+use regex::Regex;
+
+use crate::file::Variable;
+use crate::hcl;
+
+lazy_static! {
+    static ref VAR_REGEX: Regex =
+        Regex::new(r#"(?:^|\s)--?var(?:=|\s+)['"]?([A-Za-z_][\w-]*)="#).unwrap();
+    static ref VAR_FILE_REGEX: Regex =
+        Regex::new(r#"(?:^|\s)--?var-file(?:=|\s+)['"]?([^\s'"]+)"#).unwrap();
+    static ref SHELL_VARIABLE_REGEX: Regex =
+        Regex::new(r#"\$(?:\{[^}]*\}|\([^)]*\)|\w+)"#).unwrap();
+}
+
+/// What the scripts under a directory pass to Terraform.
+#[derive(Debug, Default)]
+pub struct ScriptFlags {
+    /// `-var name=value` assignments, named by the variable
+    pub values: Vec<Variable>,
+    /// `-var-file` arguments, named by the path as written
+    pub var_files: Vec<Variable>,
+}
+
+impl ScriptFlags {
+    /// Var files a `-var-file` argument refers to. The path is taken
+    /// relative to the directory the script runs in, and shell variables in
+    /// it match any file, so `envs/$ENV.tfvars` gives every var file in
+    /// `envs`.
+    pub fn resolve_var_file(var_file: &Variable, root: &Path) -> Vec<PathBuf> {
+        let pattern = SHELL_VARIABLE_REGEX.replace_all(&var_file.name, "*");
+        let path = run_dir(var_file, root).join(pattern.as_ref());
+        if pattern == var_file.name {
+            return vec![path];
+        }
+        match path.to_str().map(glob) {
+            Some(Ok(paths)) => paths.filter_map(Result::ok).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Flags of the scripts that belong to the module in `dir`, the closest
+    /// of `module_dirs` at or above the directory a script runs in.
+    pub fn of_module(&self, dir: &Path, module_dirs: &[PathBuf], root: &Path) -> ScriptFlags {
+        let belongs = |flag: &&Variable| {
+            let run_dir = run_dir(flag, root);
+            let owner = module_dirs
+                .iter()
+                .filter(|m| run_dir.starts_with(m))
+                .max_by_key(|m| m.components().count());
+            owner.is_some_and(|owner| owner == dir)
+        };
+        ScriptFlags {
+            values: self.values.iter().filter(belongs).cloned().collect(),
+            var_files: self.var_files.iter().filter(belongs).cloned().collect(),
+        }
+    }
+}
+
+/// The directory the script holding `flag` runs Terraform in. CI jobs start
+/// at the root of the checkout, which `root` is taken to be; other scripts
+/// are taken to run next to where they live.
+fn run_dir<'a>(flag: &'a Variable, root: &'a Path) -> &'a Path {
+    let script = Path::new(&flag.at);
+    let ci = [".yml", ".yaml"].iter().any(|ext| flag.at.ends_with(ext));
+    if ci {
+        root
+    } else {
+        script.parent().unwrap_or(Path::new(""))
+    }
+}
+
+fn is_script(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    name == "Makefile"
+        || name == "GNUmakefile"
+        || [".mk", ".sh", ".bash", ".yml", ".yaml"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+}
+
+/// Scans every script under `root`. Hidden directories are included, as CI
+/// configuration lives in ones like `.github`, but `.terraform` and `.git`
+/// are not.
+pub fn scan(root: &Path) -> Result<ScriptFlags, String> {
+    let mut flags = ScriptFlags::default();
+    scan_dir(root, &mut flags)?;
+    Ok(flags)
+}
+
+fn scan_dir(dir: &Path, flags: &mut ScriptFlags) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Error: could not read {}: {}", dir.display(), e))?;
+    let mut paths: Vec<_> = entries.filter_map(Result::ok).map(|e| e.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if path.is_dir() && name != ".terraform" && name != ".git" {
+            scan_dir(&path, flags)?;
+        } else if path.is_file() && is_script(&path) {
+            if let Ok(contents) = fs::read_to_string(&path) {
+                parse_script(&path.to_string_lossy(), &contents, flags);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_script(path: &str, contents: &str, flags: &mut ScriptFlags) {
+    let mut pos = hcl::Pos::start();
+    for line in contents.split_inclusive('\n') {
+        let entry = |m: regex::Match| Variable {
+            name: m.as_str().to_string(),
+            at: path.to_string(),
+            span: hcl::Span {
+                start: pos.advance(&line[..m.start()]),
+                end: pos.advance(&line[..m.end()]),
+            },
+        };
+        for cap in VAR_REGEX.captures_iter(line) {
+            flags.values.push(entry(cap.get(1).unwrap()));
+        }
+        for cap in VAR_FILE_REGEX.captures_iter(line) {
+            flags.var_files.push(entry(cap.get(1).unwrap()));
+        }
+        pos = pos.advance(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() {
+        let contents = r#"
+plan:
+	terraform plan -var "image_tag=$(TAG)" -var='replicas=2' \
+	  -var-file=envs/$(ENV).tfvars --var-file "common.tfvars"
+	echo -variable=1 -var-files=x
+"#;
+        let mut flags = ScriptFlags::default();
+        parse_script("Makefile", contents, &mut flags);
+        let values: Vec<_> = flags
+            .values
+            .iter()
+            .map(|v| (v.name.as_str(), v.location()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("image_tag", "Makefile:3:23".to_string()),
+                ("replicas", "Makefile:3:47".to_string()),
+            ]
+        );
+        let var_files: Vec<_> = flags.var_files.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(var_files, vec!["envs/$(ENV).tfvars", "common.tfvars"]);
+    }
+
+    #[test]
+    fn test_scan_and_resolve() {
+        let root = Path::new("tests/fixtures/scripts");
+        let flags = scan(root).unwrap();
+        let module_dirs = vec![root.to_path_buf(), root.join("app")];
+        let of_module = |dir: &Path| {
+            let flags = flags.of_module(dir, &module_dirs, root);
+            let values: Vec<_> = flags.values.iter().map(|v| v.location()).collect();
+            let var_files: Vec<_> = flags
+                .var_files
+                .iter()
+                .flat_map(|f| ScriptFlags::resolve_var_file(f, root))
+                .collect();
+            (values, var_files)
+        };
+
+        assert_eq!(
+            of_module(root),
+            (
+                vec![
+                    format!("{}/.github/workflows/deploy.yml:12:35", root.display()),
+                    format!("{}/deploy.sh:4:8", root.display()),
+                ],
+                vec![
+                    // Relative to the checkout, not to .github/workflows
+                    root.join("envs/prod.tfvars"),
+                    root.join("envs/prod.tfvars"),
+                    root.join("envs/staging.tfvars"),
+                ]
+            )
+        );
+        assert_eq!(
+            of_module(&root.join("app")),
+            (vec![format!("{}/app/plan.sh:2:21", root.display())], vec![])
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! Terraform type constraints and a static check of literal values against
+//! them, following Terraform's conversion rules.
+
+use std::fmt;
+
+use crate::hcl::{self, ExprKind, Expression, Operator};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Any,
+    String,
+    Number,
+    Bool,
+    List(Box<Type>),
+    Set(Box<Type>),
+    Map(Box<Type>),
+    Tuple(Vec<Type>),
+    Object(Vec<ObjectAttribute>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectAttribute {
+    pub name: String,
+    pub ty: Type,
+    /// Declared with `optional(...)`
+    pub optional: bool,
+}
+
+impl Type {
+    /// Parses the `type` argument of a variable block.
+    pub fn parse(expr: &Expression) -> Result<Type, String> {
+        match &expr.kind {
+            ExprKind::Variable(keyword) => match keyword.as_str() {
+                "any" => Ok(Type::Any),
+                "string" => Ok(Type::String),
+                "number" => Ok(Type::Number),
+                "bool" => Ok(Type::Bool),
+                "list" | "set" | "map" => Err(format!(
+                    "{} needs an element type, such as {}(string)",
+                    keyword, keyword
+                )),
+                _ => Err(format!("unknown type {}", keyword)),
+            },
+            ExprKind::FunctionCall { name, args, .. } => Self::parse_call(name, args),
+            ExprKind::Parens(inner) => Self::parse(inner),
+            _ => match expr.as_literal_string() {
+                Some(s) => Self::parse_string(&s),
+                None => Err("not a type constraint".to_string()),
+            },
+        }
+    }
+
+    /// Quoted types are either the legacy `"string"`, `"list"` and `"map"`
+    /// or, in the JSON syntax, a whole type expression.
+    fn parse_string(s: &str) -> Result<Type, String> {
+        match s {
+            "string" => Ok(Type::String),
+            "list" => Ok(Type::List(Box::new(Type::Any))),
+            "map" => Ok(Type::Map(Box::new(Type::Any))),
+            _ => {
+                let expr = crate::hcl::parse_expression(s).map_err(|e| e.message)?;
+                if expr.as_literal_string().is_some() {
+                    return Err(format!("unknown type {:?}", s));
+                }
+                Self::parse(&expr)
+            }
+        }
+    }
+
+    fn parse_call(name: &str, args: &[Expression]) -> Result<Type, String> {
+        let single = || match args {
+            [arg] => Self::parse(arg).map(Box::new),
+            _ => Err(format!("{}() takes exactly one argument", name)),
+        };
+        match name {
+            "list" => Ok(Type::List(single()?)),
+            "set" => Ok(Type::Set(single()?)),
+            "map" => Ok(Type::Map(single()?)),
+            "tuple" => match args {
+                [arg] => match &arg.kind {
+                    ExprKind::Tuple(items) => Ok(Type::Tuple(
+                        items.iter().map(Self::parse).collect::<Result<_, _>>()?,
+                    )),
+                    _ => Err("tuple() takes a list of types".to_string()),
+                },
+                _ => Err("tuple() takes exactly one argument".to_string()),
+            },
+            "object" => match args {
+                [arg] => match &arg.kind {
+                    ExprKind::Object(items) => {
+                        let mut attributes = vec![];
+                        for item in items {
+                            let name = item.key.as_key().ok_or_else(|| {
+                                "object attribute names must be static".to_string()
+                            })?;
+                            attributes.push(Self::parse_attribute(name, &item.value)?);
+                        }
+                        Ok(Type::Object(attributes))
+                    }
+                    _ => Err("object() takes an object of attribute types".to_string()),
+                },
+                _ => Err("object() takes exactly one argument".to_string()),
+            },
+            "optional" => Err("optional() is only allowed for object attributes".to_string()),
+            _ => Err(format!("unknown type constructor {}()", name)),
+        }
+    }
+
+    fn parse_attribute(name: String, expr: &Expression) -> Result<ObjectAttribute, String> {
+        if let ExprKind::FunctionCall { name: f, args, .. } = &expr.kind {
+            if f == "optional" {
+                return match args.as_slice() {
+                    [ty] | [ty, _] => Ok(ObjectAttribute {
+                        name,
+                        ty: Self::parse(ty)?,
+                        optional: true,
+                    }),
+                    _ => Err("optional() takes a type and an optional default".to_string()),
+                };
+            }
+        }
+        Ok(ObjectAttribute {
+            name,
+            ty: Self::parse(expr)?,
+            optional: false,
+        })
+    }
+
+    /// Problems with converting `value` to this type. Values that are not
+    /// literals can't be checked statically and always pass.
+    pub fn check(&self, value: &Expression) -> Vec<String> {
+        let mut errors = vec![];
+        self.check_at(value, "", &mut errors);
+        errors
+    }
+
+    fn check_at(&self, value: &Expression, path: &str, errors: &mut Vec<String>) {
+        let kind = match Literal::of(value) {
+            Some(kind) => kind,
+            None => return,
+        };
+        let mut mismatch = || {
+            let at = if path.is_empty() {
+                String::new()
+            } else {
+                format!("{}: ", path)
+            };
+            errors.push(format!("{}expected {}, got {}", at, self, kind.name()));
+        };
+
+        match (self, &kind) {
+            (Type::Any, _) | (_, Literal::Null) => {}
+            (Type::String, Literal::String(_))
+            | (Type::String, Literal::Number)
+            | (Type::String, Literal::Bool) => {}
+            (Type::Number, Literal::Number) => {}
+            (Type::Number, Literal::String(s)) if s.trim().parse::<f64>().is_ok() => {}
+            (Type::Bool, Literal::Bool) => {}
+            (Type::Bool, Literal::String(s)) if s == "true" || s == "false" => {}
+            (Type::List(element), Literal::Tuple(items))
+            | (Type::Set(element), Literal::Tuple(items)) => {
+                for (i, item) in items.iter().enumerate() {
+                    element.check_at(item, &format!("{}[{}]", path, i), errors);
+                }
+            }
+            (Type::Tuple(elements), Literal::Tuple(items)) => {
+                if elements.len() != items.len() {
+                    mismatch();
+                    return;
+                }
+                for (i, (element, item)) in elements.iter().zip(items.iter()).enumerate() {
+                    element.check_at(item, &format!("{}[{}]", path, i), errors);
+                }
+            }
+            (Type::Map(element), Literal::Object(items)) => {
+                for (key, item) in items {
+                    element.check_at(item, &format!("{}[{:?}]", path, key), errors);
+                }
+            }
+            (Type::Object(attributes), Literal::Object(items)) => {
+                for attribute in attributes {
+                    let attr_path = format!("{}.{}", path, attribute.name);
+                    match items.iter().find(|(key, _)| *key == attribute.name) {
+                        Some((_, item)) => attribute.ty.check_at(item, &attr_path, errors),
+                        None if attribute.optional => {}
+                        None => {
+                            errors.push(format!("{}: required attribute is missing", attr_path))
+                        }
+                    }
+                }
+            }
+            _ => mismatch(),
+        }
+    }
+}
+
+/// Attributes of an `object(...)` type constraint, nested objects
+/// included as `outer.inner`, each with the span of its name. Attributes of
+/// objects inside collections are left out, as references to them go
+/// through an index. A quoted constraint, as in the JSON syntax, has no
+/// spans of its own and all attributes get the span of `expr`.
+pub fn object_attributes(expr: &Expression) -> Vec<(String, hcl::Span)> {
+    let mut attributes = vec![];
+    match expr.as_literal_string() {
+        Some(s) => {
+            if let Ok(parsed) = hcl::parse_expression(&s) {
+                collect_attributes(&parsed, "", &mut attributes);
+            }
+            for attribute in attributes.iter_mut() {
+                attribute.1 = expr.span;
+            }
+        }
+        None => collect_attributes(expr, "", &mut attributes),
+    }
+    attributes
+}
+
+fn collect_attributes(expr: &Expression, prefix: &str, out: &mut Vec<(String, hcl::Span)>) {
+    let items = match &expr.kind {
+        ExprKind::FunctionCall { name, args, .. } if name == "object" => match args.as_slice() {
+            [arg] => match &arg.kind {
+                ExprKind::Object(items) => items,
+                _ => return,
+            },
+            _ => return,
+        },
+        ExprKind::FunctionCall { name, args, .. } if name == "optional" => {
+            if let Some(ty) = args.first() {
+                collect_attributes(ty, prefix, out);
+            }
+            return;
+        }
+        ExprKind::Parens(inner) => return collect_attributes(inner, prefix, out),
+        _ => return,
+    };
+    for item in items {
+        if let Some(name) = item.key.as_key() {
+            let path = format!("{}{}", prefix, name);
+            out.push((path.clone(), item.key.span));
+            collect_attributes(&item.value, &format!("{}.", path), out);
+        }
+    }
+}
+
+/// The shape of a literal value, as far as type checking is concerned.
+enum Literal<'a> {
+    Null,
+    String(String),
+    Number,
+    Bool,
+    Tuple(Vec<&'a Expression>),
+    Object(Vec<(String, &'a Expression)>),
+}
+
+impl<'a> Literal<'a> {
+    fn of(value: &'a Expression) -> Option<Literal<'a>> {
+        let literal = match &value.kind {
+            ExprKind::Null => Literal::Null,
+            ExprKind::Bool(_) => Literal::Bool,
+            ExprKind::Number(_) => Literal::Number,
+            ExprKind::Unary(Operator::Negate, operand) => match operand.kind {
+                ExprKind::Number(_) => Literal::Number,
+                _ => return None,
+            },
+            ExprKind::Parens(inner) => return Self::of(inner),
+            ExprKind::Template(_) => Literal::String(value.as_literal_string()?),
+            ExprKind::Tuple(items) => Literal::Tuple(items.iter().collect()),
+            ExprKind::Object(items) => Literal::Object(
+                items
+                    .iter()
+                    .map(|item| Some((item.key.as_key()?, &item.value)))
+                    .collect::<Option<_>>()?,
+            ),
+            _ => return None,
+        };
+        Some(literal)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Literal::Null => "null",
+            Literal::String(_) => "string",
+            Literal::Number => "number",
+            Literal::Bool => "bool",
+            Literal::Tuple(_) => "tuple",
+            Literal::Object(_) => "object",
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Any => write!(f, "any"),
+            Type::String => write!(f, "string"),
+            Type::Number => write!(f, "number"),
+            Type::Bool => write!(f, "bool"),
+            Type::List(t) => write!(f, "list({})", t),
+            Type::Set(t) => write!(f, "set({})", t),
+            Type::Map(t) => write!(f, "map({})", t),
+            Type::Tuple(ts) => {
+                let ts: Vec<_> = ts.iter().map(Type::to_string).collect();
+                write!(f, "tuple([{}])", ts.join(", "))
+            }
+            Type::Object(attributes) => {
+                let attributes: Vec<_> = attributes
+                    .iter()
+                    .map(|a| {
+                        if a.optional {
+                            format!("{} = optional({})", a.name, a.ty)
+                        } else {
+                            format!("{} = {}", a.name, a.ty)
+                        }
+                    })
+                    .collect();
+                write!(f, "object({{{}}})", attributes.join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hcl;
+
+    fn parse_type(src: &str) -> Result<Type, String> {
+        Type::parse(&hcl::parse_expression(src).unwrap())
+    }
+
+    fn check(ty: &str, value: &str) -> Vec<String> {
+        parse_type(ty)
+            .unwrap()
+            .check(&hcl::parse_expression(value).unwrap())
+    }
+
+    #[test]
+    fn test_parse_types() {
+        assert_eq!(parse_type("string"), Ok(Type::String));
+        assert_eq!(parse_type(r#""list""#), Ok(Type::List(Box::new(Type::Any))));
+        assert_eq!(
+            parse_type(r#""map(number)""#),
+            Ok(Type::Map(Box::new(Type::Number)))
+        );
+        assert_eq!(
+            parse_type("object({ a = string, b = optional(list(number), []) })")
+                .unwrap()
+                .to_string(),
+            "object({a = string, b = optional(list(number))})"
+        );
+        assert!(parse_type("strnig").is_err());
+        assert!(parse_type("optional(string)").is_err());
+    }
+
+    #[test]
+    fn test_object_attributes() {
+        let src = "object({ a = string, db = optional(object({ port = number })), l = list(object({ x = bool })) })";
+        let names: Vec<_> = object_attributes(&hcl::parse_expression(src).unwrap())
+            .into_iter()
+            .map(|(name, span)| format!("{} {}", name, span.start.column))
+            .collect();
+        assert_eq!(names, vec!["a 10", "db 22", "db.port 45", "l 64"]);
+        assert!(object_attributes(&hcl::parse_expression("map(string)").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_primitive_conversions() {
+        assert!(check("number", r#""5""#).is_empty());
+        assert_eq!(
+            check("number", r#""five""#),
+            vec!["expected number, got string"]
+        );
+        assert!(check("string", "42").is_empty());
+        assert!(check("bool", r#""true""#).is_empty());
+        assert_eq!(check("bool", "1"), vec!["expected bool, got number"]);
+        assert!(check("number", "null").is_empty());
+        assert!(check("number", "var.x").is_empty());
+    }
+
+    #[test]
+    fn test_collection_and_object_values() {
+        assert_eq!(
+            check("list(number)", r#"[1, "two", -3]"#),
+            vec!["[1]: expected number, got string"]
+        );
+        assert_eq!(
+            check("map(string)", r#"{ a = "x", b = { c = 1 } }"#),
+            vec![r#"["b"]: expected string, got object"#]
+        );
+        assert_eq!(
+            check(
+                "object({ name = string, port = number, tags = optional(map(string)) })",
+                r#"{ port = "http" }"#
+            ),
+            vec![
+                ".name: required attribute is missing",
+                ".port: expected number, got string",
+            ]
+        );
+        assert_eq!(
+            check("tuple([string, bool])", r#"["a"]"#),
+            vec!["expected tuple([string, bool]), got tuple"]
+        );
+    }
+}
@@ -0,0 +1,8 @@
+provider "aws" {
+  region = var.region
+}
+
+resource "aws_instance" "app" {
+  count         = var.instance_count
+  instance_type = var.instance_type
+}
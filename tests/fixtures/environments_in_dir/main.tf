@@ -0,0 +1,15 @@
+variable "region" {
+  type = string
+}
+
+variable "instance_count" {
+  type = number
+}
+
+provider "aws" {
+  region = var.region
+}
+
+resource "aws_instance" "app" {
+  count = var.instance_count
+}
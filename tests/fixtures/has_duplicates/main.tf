@@ -0,0 +1,9 @@
+variable "region" {}
+
+provider "aws" {
+  region = var.region
+}
+
+resource "aws_subnet" "main" {
+  availability_zone = var.zone
+}
@@ -0,0 +1,3 @@
+output "used" {
+  value = var.used +
+}
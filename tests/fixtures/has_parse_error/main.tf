@@ -0,0 +1,7 @@
+variable "used" {
+  default = 1
+}
+
+variable "required" {
+  type = string
+}
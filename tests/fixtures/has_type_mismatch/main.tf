@@ -0,0 +1,13 @@
+resource "aws_instance" "app" {
+  count = var.instance_count
+  tags  = var.service.tags
+
+  availability_zone = var.zones[count.index]
+  monitoring        = var.enabled
+
+  lifecycle {
+    create_before_destroy = var.retries > 0
+  }
+
+  name = var.service.name
+}
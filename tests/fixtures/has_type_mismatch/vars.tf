@@ -0,0 +1,25 @@
+variable "instance_count" {
+  type = number
+}
+
+variable "zones" {
+  type = list(string)
+}
+
+variable "service" {
+  type = object({
+    name = string
+    port = optional(number, 80)
+    tags = optional(map(string), {})
+  })
+}
+
+variable "retries" {
+  # Typo, Terraform would reject this constraint
+  default = 3
+  type    = strnig
+}
+
+variable "enabled" {
+  type = bool
+}
@@ -0,0 +1,10 @@
+variable "instance_type" {
+  default = "t3.micro"
+}
+
+resource "aws_instance" "web" {
+  instance_type = var.instance_type
+  ami           = var.ami_id
+
+  tags = { Env = var.enviroment }
+}
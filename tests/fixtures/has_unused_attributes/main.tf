@@ -0,0 +1,6 @@
+resource "aws_db_instance" "main" {
+  identifier = var.settings.a
+  address    = var.settings.db.host
+  multi_az   = var.settings.c ? true : false
+  tags       = var.tags
+}
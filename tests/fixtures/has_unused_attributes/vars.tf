@@ -0,0 +1,18 @@
+variable "settings" {
+  type = object({
+    a = string
+    b = number
+    c = optional(bool, false)
+    db = optional(object({
+      host = string
+      port = number
+    }))
+  })
+}
+
+variable "tags" {
+  type = object({
+    owner = string
+    team  = string
+  })
+}
@@ -0,0 +1,23 @@
+resource "aws_iam_policy" "s3" {
+  policy = file("${path.module}/policies/s3.json")
+}
+
+resource "aws_instance" "web" {
+  ami       = "ami-0123456789"
+  user_data = templatefile("${path.module}/templates/${terraform.workspace}.tftpl", {})
+  tags      = jsondecode(file("tags.json"))
+}
+
+resource "aws_s3_object" "scripts" {
+  for_each = fileset(path.module, "scripts/init-*.sh")
+  key      = each.value
+  source   = "${path.module}/${each.value}"
+}
+
+locals {
+  extra_tags = fileexists("${path.module}/extra.json") ? file("${path.module}/extra.json") : "{}"
+}
+
+output "extra_tags" {
+  value = local.extra_tags
+}
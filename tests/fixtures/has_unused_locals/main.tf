@@ -0,0 +1,12 @@
+locals {
+  name      = "web"
+  forgotten = "nobody reads me"
+  tags = {
+    Name = local.name
+  }
+}
+
+resource "aws_instance" "web" {
+  ami  = "ami-123"
+  tags = local.tags
+}
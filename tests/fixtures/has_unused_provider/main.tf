@@ -0,0 +1,13 @@
+provider "cloudflare" {}
+
+resource "random_id" "suffix" {
+  byte_length = 4
+}
+
+resource "aws_s3_bucket" "logs" {
+  bucket = "logs-${random_id.suffix.hex}"
+}
+
+resource "google_compute_instance" "beta" {
+  provider = google-beta
+}
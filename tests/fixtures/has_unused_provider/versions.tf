@@ -0,0 +1,22 @@
+terraform {
+  required_version = ">= 1.3"
+
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws"
+      version = "~> 5.0"
+    }
+    random = {
+      source = "hashicorp/random"
+    }
+    tls = {
+      source = "hashicorp/tls"
+    }
+    google-beta = {
+      source = "hashicorp/google-beta"
+    }
+    cloudflare = {
+      source = "cloudflare/cloudflare"
+    }
+  }
+}
@@ -0,0 +1,12 @@
+resource "aws_acm_certificate" "cdn" {
+  provider    = aws.us_east_1
+  domain_name = "example.com"
+}
+
+module "dns" {
+  source = "terraform-aws-modules/route53/aws"
+
+  providers = {
+    aws = aws.dns
+  }
+}
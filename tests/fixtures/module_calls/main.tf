@@ -0,0 +1,21 @@
+module "net" {
+  source = "./modules/net"
+
+  cidr   = "10.0.0.0/16"
+  colour = "blue"
+}
+
+module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.0.0"
+
+  anything_goes = true
+}
+
+module "gone" {
+  source = "./modules/gone"
+}
+
+output "network" {
+  value = module.net.vpc_id
+}
@@ -0,0 +1,22 @@
+variable "cidr" {}
+
+variable "name" {
+  type = string
+}
+
+variable "tags" {
+  default = {}
+}
+
+resource "aws_vpc" "main" {
+  cidr_block = var.cidr
+  tags       = merge(var.tags, { Name = var.name })
+}
+
+output "vpc_id" {
+  value = aws_vpc.main.id
+}
+
+output "vpc_arn" {
+  value = aws_vpc.main.arn
+}
@@ -0,0 +1 @@
+variable "downloaded_and_not_ours" {}
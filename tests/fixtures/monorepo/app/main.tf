@@ -0,0 +1,5 @@
+variable "image" {}
+
+resource "aws_instance" "app" {
+  ami = var.image
+}
@@ -0,0 +1,7 @@
+variable "cidr" {}
+
+variable "unused_in_network" {}
+
+resource "aws_vpc" "main" {
+  cidr_block = var.cidr
+}
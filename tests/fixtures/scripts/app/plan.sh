@@ -0,0 +1,2 @@
+#!/bin/sh
+terraform plan -var image=app:latest
@@ -0,0 +1,5 @@
+#!/bin/sh
+set -e
+terraform apply \
+  -var legacy_mode=true \
+  -var-file="envs/${ENV}.tfvars"
@@ -0,0 +1,12 @@
+variable "image_tag" {}
+
+variable "replicas" {}
+
+resource "kubernetes_deployment" "app" {
+  spec {
+    replicas = var.replicas
+    template {
+      image = "app:${var.image_tag}"
+    }
+  }
+}
@@ -0,0 +1,14 @@
+variable "cidr" {}
+
+variable "name" {
+  type = string
+}
+
+variable "tags" {
+  default = {}
+}
+
+resource "aws_vpc" "main" {
+  cidr_block = var.cidr
+  tags       = merge(var.tags, { Name = var.name })
+}